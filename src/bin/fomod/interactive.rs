@@ -0,0 +1,227 @@
+use std::io::{self, BufRead, Write};
+
+use fomod::{GameEnvironment, GroupType, InstallerSession, PluginTypeEnum};
+
+const HELP: &str = "\
+  <group>.<plugin>    select a plugin, or toggle it in a checkbox group
+  i <group>.<plugin>  show the description of a plugin
+  n                   next step (also an empty line)
+  b                   back to the previous step
+  q                   quit without installing";
+
+/// Walks the session through its steps with commands read from `input`.
+///
+/// Returns `false` if the user quit before the last step.
+pub fn run<E: GameEnvironment + ?Sized>(
+    session: &mut InstallerSession<'_, E>,
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+) -> io::Result<bool> {
+    if !session.module_dependencies_met() {
+        writeln!(output, "! the dependencies of this module are not met")?;
+    }
+
+    let mut line = String::new();
+    while session.current_step().is_some() {
+        render(session, output)?;
+        write!(output, "> ")?;
+        output.flush()?;
+
+        line.clear();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(false);
+        }
+        let command = line.trim();
+        match command {
+            "" | "n" => {
+                if let Err(e) = session.next() {
+                    writeln!(output, "! {e}")?;
+                }
+            }
+            "b" => {
+                if session.previous().is_none() {
+                    writeln!(output, "! this is the first step")?;
+                }
+            }
+            "q" => return Ok(false),
+            "h" | "?" => writeln!(output, "{HELP}")?,
+            _ => {
+                let (info, target) = match command.strip_prefix("i ") {
+                    Some(target) => (true, target.trim()),
+                    None => (false, command),
+                };
+                match plugin(session, target) {
+                    Some((group, plugin)) if info => {
+                        let plugin = session.plugins(group)[plugin];
+                        writeln!(output, "{}\n{}", plugin.name, plugin.description.trim())?;
+                    }
+                    Some((group, plugin)) => {
+                        if let Err(message) = toggle(session, group, plugin) {
+                            writeln!(output, "! {message}")?;
+                        }
+                    }
+                    None => writeln!(output, "! unknown command '{command}', h for help")?,
+                }
+            }
+        }
+    }
+    Ok(true)
+}
+
+fn render<E: GameEnvironment + ?Sized>(
+    session: &InstallerSession<'_, E>,
+    output: &mut impl Write,
+) -> io::Result<()> {
+    let Some(step) = session.current_step() else {
+        return Ok(());
+    };
+    writeln!(output, "\n== {} ==", step.name)?;
+    for (gidx, group) in session.groups().into_iter().enumerate() {
+        let (radio, rule) = match &group.plugins {
+            GroupType::SelectExactlyOne(_) => (true, "select exactly one"),
+            GroupType::SelectAtMostOne(_) => (true, "select at most one"),
+            GroupType::SelectAtLeastOne(_) => (false, "select at least one"),
+            GroupType::SelectAll(_) => (false, "select all"),
+            GroupType::SelectAny(_) => (false, "select any"),
+        };
+        writeln!(output, "{}. {} ({rule})", gidx + 1, group.name)?;
+
+        for (pidx, plugin) in session.plugins(gidx).into_iter().enumerate() {
+            let mark = match (radio, session.is_selected(gidx, pidx)) {
+                (true, true) => "(*)",
+                (true, false) => "( )",
+                (false, true) => "[x]",
+                (false, false) => "[ ]",
+            };
+            let hint = match session.plugin_type(gidx, pidx) {
+                Ok(PluginTypeEnum::Optional) | Err(_) => "",
+                Ok(PluginTypeEnum::Required) => "  required",
+                Ok(PluginTypeEnum::Recommended) => "  recommended",
+                Ok(PluginTypeEnum::NotUsable) => "  not usable",
+                Ok(PluginTypeEnum::CouldBeUsable) => "  could be usable",
+            };
+            writeln!(
+                output,
+                "   {}.{} {mark} {}{hint}",
+                gidx + 1,
+                pidx + 1,
+                plugin.name
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Parses `<group>.<plugin>` into indices of the current step.
+fn plugin<E: GameEnvironment + ?Sized>(
+    session: &InstallerSession<'_, E>,
+    target: &str,
+) -> Option<(usize, usize)> {
+    let (group, plugin) = target.split_once('.')?;
+    let group = group.parse::<usize>().ok()?.checked_sub(1)?;
+    let plugin = plugin.parse::<usize>().ok()?.checked_sub(1)?;
+    (group < session.groups().len() && plugin < session.plugins(group).len())
+        .then_some((group, plugin))
+}
+
+/// Selects a plugin with radio button or checkbox semantics.
+fn toggle<E: GameEnvironment + ?Sized>(
+    session: &mut InstallerSession<'_, E>,
+    group: usize,
+    plugin: usize,
+) -> Result<(), &'static str> {
+    let selected = session.is_selected(group, plugin);
+    let exactly_one = matches!(
+        session.groups()[group].plugins,
+        GroupType::SelectExactlyOne(_)
+    );
+    match session
+        .plugin_type(group, plugin)
+        .map_err(|_| "unknown plugin")?
+    {
+        PluginTypeEnum::NotUsable if !selected => Err("this plugin is not usable"),
+        PluginTypeEnum::Required if selected => Err("this plugin is required"),
+        _ if selected && exactly_one => Ok(()),
+        _ if selected => session
+            .deselect(group, plugin)
+            .map_err(|_| "unknown plugin"),
+        _ => session.select(group, plugin).map_err(|_| "unknown plugin"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fomod::{Config, InstallerSession, MemoryEnvironment};
+
+    const XML: &str = r#"
+        <config>
+            <moduleName>Example Mod</moduleName>
+            <installSteps order="Explicit">
+                <installStep name="Options">
+                    <optionalFileGroups order="Explicit">
+                        <group name="Textures" type="SelectExactlyOne">
+                            <plugins order="Explicit">
+                                <plugin name="SD"><description>Small</description></plugin>
+                                <plugin name="HD">
+                                    <description>Large</description>
+                                    <conditionFlags><flag name="hd">On</flag></conditionFlags>
+                                    <typeDescriptor><type name="Recommended"/></typeDescriptor>
+                                </plugin>
+                            </plugins>
+                        </group>
+                        <group name="Extras" type="SelectAny">
+                            <plugins order="Explicit">
+                                <plugin name="Sounds"><description/></plugin>
+                                <plugin name="Broken">
+                                    <description/>
+                                    <typeDescriptor><type name="NotUsable"/></typeDescriptor>
+                                </plugin>
+                            </plugins>
+                        </group>
+                    </optionalFileGroups>
+                </installStep>
+                <installStep name="HD Options">
+                    <visible><flagDependency flag="hd" value="On"/></visible>
+                    <optionalFileGroups order="Explicit">
+                        <group name="Size" type="SelectAtMostOne">
+                            <plugins order="Explicit">
+                                <plugin name="4K"><description/></plugin>
+                            </plugins>
+                        </group>
+                    </optionalFileGroups>
+                </installStep>
+            </installSteps>
+        </config>
+        "#;
+
+    #[test]
+    pub fn interactive_session() {
+        let config = Config::try_from(XML).unwrap();
+        let env = MemoryEnvironment::new();
+        let mut session = InstallerSession::new(&config, &env);
+
+        let input = "i 1.2\n1.1\n2.1\n2.2\n9.9\n1.2\nn\n1.1\n1.1\nb\n2.1\n\n1.1\nn\n";
+        let mut output = Vec::new();
+        let finished = super::run(&mut session, &mut input.as_bytes(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(finished);
+        assert!(output.contains("HD\nLarge"));
+        assert!(output.contains("   1.2 (*) HD  recommended"));
+        assert!(output.contains("   2.2 [ ] Broken  not usable"));
+        assert!(output.contains("! this plugin is not usable"));
+        assert!(output.contains("! unknown command '9.9'"));
+        assert!(output.contains("   1.1 ( ) 4K"));
+        assert!(output.contains("   1.1 (*) 4K"));
+
+        let selected = session
+            .selected_plugins()
+            .into_iter()
+            .map(|(_, _, plugin)| plugin.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(selected, vec!["HD", "4K"]);
+
+        let mut session = InstallerSession::new(&config, &env);
+        assert!(!super::run(&mut session, &mut "q\n".as_bytes(), &mut Vec::new()).unwrap());
+    }
+}
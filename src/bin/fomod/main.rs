@@ -0,0 +1,422 @@
+mod interactive;
+
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use clap::{Args, Parser, Subcommand};
+use fomod::{
+    lint, path::normalize, spec::decode, Choices, Config, DependencyState, Error, FileTypeEnum,
+    FomodPackage, GroupType, InstallerSession, LintCode, MemoryEnvironment, PackageSource,
+    PluginTypeDescriptorEnum,
+};
+
+/// Inspects, validates and installs fomod packages.
+#[derive(Parser)]
+#[command(name = "fomod", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Prints the steps, groups and plugins as a tree.
+    Inspect {
+        /// A package, or a ModuleConfig.xml on its own.
+        package: PathBuf,
+    },
+    /// Reports schema and semantic problems.
+    Validate {
+        /// A package, or a ModuleConfig.xml on its own.
+        package: PathBuf,
+        /// A lint code to leave out, e.g. unread-flag. May be repeated.
+        #[arg(long, value_name = "CODE")]
+        allow: Vec<LintCode>,
+    },
+    /// Prints the files which would be installed.
+    Plan {
+        package: PathBuf,
+        #[command(flatten)]
+        options: InstallOptions,
+    },
+    /// Installs into a directory.
+    Install {
+        package: PathBuf,
+        target: PathBuf,
+        /// Where to write the manifest of the installed files.
+        #[arg(long)]
+        manifest: Option<PathBuf>,
+        #[command(flatten)]
+        options: InstallOptions,
+    },
+}
+
+#[derive(Args)]
+struct InstallOptions {
+    /// The choices to replay: a choices document, a Mod Organizer 2
+    /// meta.ini or Vortex installerChoices json. The defaults of every step
+    /// are used without it.
+    #[arg(long, conflicts_with = "interactive")]
+    choices: Option<PathBuf>,
+    /// Makes the choices in the terminal, step by step.
+    #[arg(short, long)]
+    interactive: bool,
+    /// Saves the choices as a choices document, to replay them later.
+    #[arg(long, value_name = "FILE")]
+    save_choices: Option<PathBuf>,
+    /// A game file which is active, e.g. a plugin. May be repeated.
+    #[arg(long = "active", value_name = "FILE")]
+    active: Vec<String>,
+    #[arg(long, value_name = "VERSION")]
+    game_version: Option<String>,
+}
+impl InstallOptions {
+    fn environment(&self) -> MemoryEnvironment {
+        let mut env = MemoryEnvironment::new();
+        for file in &self.active {
+            env.set_file(file, DependencyState::Active);
+        }
+        env.set_game_version(self.game_version.as_deref());
+        env
+    }
+
+    /// Makes the choices and saves them if asked to.
+    fn session<'a>(
+        &self,
+        config: &'a Config,
+        env: &'a MemoryEnvironment,
+    ) -> Result<InstallerSession<'a, MemoryEnvironment>, String> {
+        let session = self.choose(config, env)?;
+        if let Some(path) = &self.save_choices {
+            let file =
+                File::create(path).map_err(|e| Error::from(e).with_file(path).to_string())?;
+            Choices::from_session(&session)
+                .write_to(file)
+                .map_err(|e| e.with_file(path).to_string())?;
+        }
+        Ok(session)
+    }
+
+    fn choose<'a>(
+        &self,
+        config: &'a Config,
+        env: &'a MemoryEnvironment,
+    ) -> Result<InstallerSession<'a, MemoryEnvironment>, String> {
+        let Some(path) = &self.choices else {
+            let mut session = InstallerSession::new(config, env);
+            if self.interactive {
+                let finished =
+                    interactive::run(&mut session, &mut io::stdin().lock(), &mut io::stdout())
+                        .map_err(|e| e.to_string())?;
+                if !finished {
+                    return Err("cancelled".to_string());
+                }
+            }
+            while session.current_step().is_some() {
+                session.next().map_err(|e| e.to_string())?;
+            }
+            return Ok(session);
+        };
+
+        let contents = fs::read(path).map_err(|e| Error::from(e).with_file(path).to_string())?;
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        let choices = match extension.to_lowercase().as_str() {
+            "ini" => Choices::from_mo2_meta(&String::from_utf8_lossy(&contents)),
+            "json" => Choices::from_vortex_json(&String::from_utf8_lossy(&contents)),
+            _ => Choices::try_from(contents.as_slice()),
+        }
+        .map_err(|e| e.with_file(path).to_string())?;
+        choices
+            .replay(config, env)
+            .map_err(|e| format!("{}: {e}", path.display()))
+    }
+}
+
+fn main() -> ExitCode {
+    let result = match Cli::parse().command {
+        Command::Inspect { package } => inspect(&package),
+        Command::Validate { package, allow } => validate(&package, &allow, &mut io::stdout()),
+        Command::Plan { package, options } => plan(&package, &options, &mut io::stdout()),
+        Command::Install {
+            package,
+            target,
+            manifest,
+            options,
+        } => install(&package, &target, manifest.as_deref(), &options),
+    };
+
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn is_module_config(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("xml"))
+}
+
+fn open(path: &Path) -> Result<FomodPackage<Box<dyn PackageSource>>, String> {
+    FomodPackage::open(path).map_err(|e| e.to_string())
+}
+
+fn load_config(path: &Path) -> Result<Config, String> {
+    if is_module_config(path) {
+        let file = File::open(path).map_err(|e| Error::from(e).with_file(path).to_string())?;
+        Config::try_from(BufReader::new(file)).map_err(|e| e.with_file(path).to_string())
+    } else {
+        Ok(open(path)?.config().clone())
+    }
+}
+
+fn inspect(path: &Path) -> Result<ExitCode, String> {
+    let config = load_config(path)?;
+    println!("{}", config.module_name);
+    if !config.required_install_files.is_empty() {
+        println!("  required files: {}", config.required_install_files.len());
+    }
+    for step in config.install_steps.sorted() {
+        let visible = if step.visible.is_some() {
+            " (conditional)"
+        } else {
+            ""
+        };
+        println!("  step: {}{visible}", step.name);
+        for group in step.optional_file_groups.sorted() {
+            let typ = match &group.plugins {
+                GroupType::SelectAtLeastOne(_) => "SelectAtLeastOne",
+                GroupType::SelectAtMostOne(_) => "SelectAtMostOne",
+                GroupType::SelectExactlyOne(_) => "SelectExactlyOne",
+                GroupType::SelectAll(_) => "SelectAll",
+                GroupType::SelectAny(_) => "SelectAny",
+            };
+            println!("    group: {} [{typ}]", group.name);
+            for plugin in group.plugins.inner().sorted() {
+                let typ = match &plugin.type_descriptor {
+                    Some(PluginTypeDescriptorEnum::PluginType(typ)) => format!("{typ:?}"),
+                    Some(PluginTypeDescriptorEnum::DependencyType(dt)) => {
+                        format!("{:?}, conditional", dt.default_type)
+                    }
+                    None => "Optional".to_string(),
+                };
+                println!(
+                    "      plugin: {} [{typ}] {} file(s)",
+                    plugin.name,
+                    plugin.files.len()
+                );
+            }
+        }
+    }
+    if !config.conditional_file_installs.is_empty() {
+        println!(
+            "  conditional patterns: {}",
+            config.conditional_file_installs.len()
+        );
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn validate(path: &Path, allow: &[LintCode], out: &mut impl Write) -> Result<ExitCode, String> {
+    let mut problems = Vec::new();
+    let (config, xml) = if is_module_config(path) {
+        let xml = fs::read(path)
+            .map_err(Error::from)
+            .and_then(|bytes| decode(&bytes))
+            .map_err(|e| e.with_file(path).to_string())?;
+        (load_config(path)?, xml)
+    } else {
+        let mut package = open(path)?;
+        for source in package.missing_sources() {
+            problems.push(format!("missing source '{source}'"));
+        }
+        let xml = package
+            .read("fomod/ModuleConfig.xml")
+            .and_then(|bytes| decode(&bytes))
+            .map_err(|e| e.to_string())?;
+        (package.config().clone(), xml)
+    };
+
+    let mut files = config.required_install_files.iter().collect::<Vec<_>>();
+    for step in config.install_steps.list() {
+        for group in step.optional_file_groups.list() {
+            for plugin in group.plugins.inner().list() {
+                files.extend(&plugin.files);
+            }
+        }
+    }
+    files.extend(
+        config
+            .conditional_file_installs
+            .iter()
+            .flat_map(|cip| &cip.files),
+    );
+    for file in files {
+        let (FileTypeEnum::File(ft) | FileTypeEnum::Folder(ft)) = file;
+        let destination = ft.destination.as_deref().unwrap_or("");
+        for path in [ft.source.as_str(), destination] {
+            if let Err(e) = normalize(path) {
+                problems.push(e.to_string());
+            }
+        }
+    }
+
+    for lint in lint(&config) {
+        if allow.contains(&lint.code) {
+            continue;
+        }
+        match lint.position(&xml) {
+            Some(position) => problems.push(format!("{position}: {lint}")),
+            None => problems.push(lint.to_string()),
+        }
+    }
+
+    for problem in &problems {
+        writeln!(out, "{problem}").map_err(|e| e.to_string())?;
+    }
+    if problems.is_empty() {
+        writeln!(out, "{}: ok", path.display()).map_err(|e| e.to_string())?;
+        Ok(ExitCode::SUCCESS)
+    } else {
+        Ok(ExitCode::FAILURE)
+    }
+}
+
+fn plan(path: &Path, options: &InstallOptions, out: &mut impl Write) -> Result<ExitCode, String> {
+    let package = open(path)?;
+    let config = package.config().clone();
+    let env = options.environment();
+    let session = options.session(&config, &env)?;
+
+    let operations = package
+        .operations(&session.install_plan())
+        .map_err(|e| e.to_string())?;
+    for op in operations {
+        writeln!(out, "{} -> {}", op.source, op.destination).map_err(|e| e.to_string())?;
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn install(
+    path: &Path,
+    target: &Path,
+    manifest_path: Option<&Path>,
+    options: &InstallOptions,
+) -> Result<ExitCode, String> {
+    let mut package = open(path)?;
+    let config = package.config().clone();
+    let env = options.environment();
+    let session = options.session(&config, &env)?;
+
+    let manifest = package
+        .install(&session.install_plan(), target)
+        .map_err(|e| e.to_string())?;
+    if let Some(manifest_path) = manifest_path {
+        let file = File::create(manifest_path)
+            .map_err(|e| Error::from(e).with_file(manifest_path).to_string())?;
+        manifest
+            .write_to(file)
+            .map_err(|e| e.with_file(manifest_path).to_string())?;
+    }
+    println!(
+        "installed {} file(s) into {}",
+        manifest.files.len(),
+        target.display()
+    );
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, process::ExitCode};
+
+    use super::{plan, validate, InstallOptions};
+
+    const XML: &str = r#"<config>
+    <moduleName>Example Mod</moduleName>
+    <installSteps order="Explicit">
+        <installStep name="Options">
+            <optionalFileGroups order="Explicit">
+                <group name="Textures" type="SelectExactlyOne">
+                    <plugins order="Explicit">
+                        <plugin name="HD">
+                            <description/>
+                            <files><folder source="hd" destination="textures"/></files>
+                            <conditionFlags><flag name="hd">On</flag></conditionFlags>
+                        </plugin>
+                    </plugins>
+                </group>
+            </optionalFileGroups>
+        </installStep>
+    </installSteps>
+</config>
+"#;
+
+    fn options() -> InstallOptions {
+        InstallOptions {
+            choices: None,
+            interactive: false,
+            save_choices: None,
+            active: Vec::new(),
+            game_version: None,
+        }
+    }
+
+    #[test]
+    pub fn validate_config() {
+        let path = std::env::temp_dir().join(format!("fomod-validate-{}.xml", std::process::id()));
+        fs::write(&path, XML).unwrap();
+
+        let mut out = Vec::new();
+        let code = validate(&path, &[], &mut out).unwrap();
+        assert_eq!(code, ExitCode::FAILURE);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "line 11, column 45: installSteps/installStep[1]/optionalFileGroups/group[1]/plugins/plugin[1]/conditionFlags/flag[1]: flag 'hd' is never read [unread-flag]\n"
+        );
+
+        let mut out = Vec::new();
+        let allow = ["unread-flag".parse().unwrap()];
+        assert_eq!(
+            validate(&path, &allow, &mut out).unwrap(),
+            ExitCode::SUCCESS
+        );
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format!("{}: ok\n", path.display())
+        );
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    pub fn plan_package() {
+        let root = std::env::temp_dir().join(format!("fomod-plan-{}", std::process::id()));
+        let files = [
+            ("fomod/ModuleConfig.xml", XML),
+            ("hd/a.dds", "a"),
+            ("hd/sub/b.dds", "b"),
+        ];
+        for (path, contents) in files {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+
+        let mut out = Vec::new();
+        let code = plan(&root, &options(), &mut out);
+        fs::remove_dir_all(&root).unwrap();
+        assert_eq!(code.unwrap(), ExitCode::SUCCESS);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "hd/a.dds -> textures/a.dds\nhd/sub/b.dds -> textures/sub/b.dds\n"
+        );
+    }
+}
@@ -0,0 +1,423 @@
+mod mo2;
+#[cfg(feature = "vortex")]
+mod vortex;
+
+use std::{
+    fmt,
+    io::{self, BufReader},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{spec, Config, Error, GameEnvironment, InstallerSession, SelectionError};
+
+/// The plugins selected in every visited step, by name.
+///
+/// A saved session which can be replayed for an unattended installation.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct Choices {
+    #[serde(rename = "step", default)]
+    pub steps: Vec<StepChoices>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct StepChoices {
+    #[serde(rename = "@name")]
+    pub name: String,
+    #[serde(rename = "group", default)]
+    pub groups: Vec<GroupChoices>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct GroupChoices {
+    #[serde(rename = "@name")]
+    pub name: String,
+    #[serde(rename = "plugin", default)]
+    pub plugins: Vec<PluginName>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct PluginName {
+    #[serde(rename = "@name")]
+    pub name: String,
+}
+
+/// A reason why choices do not fit a `Config`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ChoicesError {
+    /// A step of the choices which the config does not have.
+    UnknownStep(String),
+    /// A step of the choices which is skipped with these choices.
+    StepNotVisible(String),
+    /// A visible step without choices.
+    MissingStep(String),
+    UnknownGroup {
+        step: String,
+        group: String,
+    },
+    MissingGroup {
+        step: String,
+        group: String,
+    },
+    /// The choices of a step do not fit its groups.
+    Selection {
+        step: String,
+        error: SelectionError,
+    },
+}
+impl fmt::Display for ChoicesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownStep(step) => write!(f, "step '{step}' does not exist"),
+            Self::StepNotVisible(step) => write!(f, "step '{step}' is not visible"),
+            Self::MissingStep(step) => write!(f, "step '{step}' has no choices"),
+            Self::UnknownGroup { step, group } => {
+                write!(f, "step '{step}': group '{group}' does not exist")
+            }
+            Self::MissingGroup { step, group } => {
+                write!(f, "step '{step}': group '{group}' has no choices")
+            }
+            Self::Selection { step, error } => write!(f, "step '{step}': {error}"),
+        }
+    }
+}
+impl std::error::Error for ChoicesError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Selection { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl Choices {
+    /// The choices made in the steps a session has visited.
+    pub fn from_session<E: GameEnvironment + ?Sized>(session: &InstallerSession<'_, E>) -> Self {
+        let mut steps = Vec::<StepChoices>::new();
+        let mut last = None;
+
+        for choice in session.choices() {
+            let (step, group) = (choice.step, choice.group);
+            match last {
+                Some((s, _)) if std::ptr::eq(s, step) => {}
+                _ => steps.push(StepChoices {
+                    name: step.name.clone(),
+                    groups: Vec::new(),
+                }),
+            }
+            let groups = &mut steps.last_mut().unwrap().groups;
+            match last {
+                Some((s, g)) if std::ptr::eq(s, step) && std::ptr::eq(g, group) => {}
+                _ => groups.push(GroupChoices {
+                    name: group.name.clone(),
+                    plugins: Vec::new(),
+                }),
+            }
+            if choice.selected {
+                groups.last_mut().unwrap().plugins.push(PluginName {
+                    name: choice.plugin.name.clone(),
+                });
+            }
+            last = Some((step, group));
+        }
+
+        Self { steps }
+    }
+
+    /// Walks a new session through the steps, making these choices.
+    ///
+    /// Every visible step must have choices, which must be valid for its
+    /// groups; choices for steps which are not visited are an error as well.
+    pub fn replay<'a, E: GameEnvironment + ?Sized>(
+        &self,
+        config: &'a Config,
+        env: &'a E,
+    ) -> Result<InstallerSession<'a, E>, ChoicesError> {
+        let mut session = InstallerSession::new(config, env);
+        let mut recorded = self.steps.iter();
+
+        while let Some(step) = session.current_step() {
+            let choices = recorded.as_slice();
+            let Some(choice) = choices.first().filter(|c| c.name == step.name) else {
+                return Err(if choices.iter().any(|c| c.name == step.name) {
+                    skipped(config, &choices[0].name)
+                } else {
+                    ChoicesError::MissingStep(step.name.clone())
+                });
+            };
+            recorded.next();
+
+            // Groups may share a name; the n-th group of a name takes the n-th
+            // choices of that name.
+            let groups = session.groups();
+            let earlier = |name: &str, before: usize| {
+                choice.groups[..before]
+                    .iter()
+                    .filter(|c| c.name == name)
+                    .count()
+            };
+            if let Some(unknown) = choice.groups.iter().enumerate().find_map(|(idx, c)| {
+                let count = groups.iter().filter(|g| g.name == c.name).count();
+                (earlier(&c.name, idx) >= count).then_some(c)
+            }) {
+                return Err(ChoicesError::UnknownGroup {
+                    step: step.name.clone(),
+                    group: unknown.name.clone(),
+                });
+            }
+
+            for (idx, group) in groups.iter().enumerate() {
+                let same_name = groups[..idx]
+                    .iter()
+                    .filter(|g| g.name == group.name)
+                    .count();
+                let group_choice = choice
+                    .groups
+                    .iter()
+                    .filter(|c| c.name == group.name)
+                    .nth(same_name);
+                let Some(group_choice) = group_choice else {
+                    if session.plugins(idx).is_empty() {
+                        continue;
+                    }
+                    return Err(ChoicesError::MissingGroup {
+                        step: step.name.clone(),
+                        group: group.name.clone(),
+                    });
+                };
+
+                let names = group_choice
+                    .plugins
+                    .iter()
+                    .map(|p| p.name.as_str())
+                    .collect::<Vec<_>>();
+                let selection_error = |error| ChoicesError::Selection {
+                    step: step.name.clone(),
+                    error,
+                };
+                let plugins = group.plugin_indices(&names).map_err(selection_error)?;
+                session
+                    .set_selected(idx, &plugins)
+                    .map_err(selection_error)?;
+            }
+
+            session.next().map_err(|error| ChoicesError::Selection {
+                step: step.name.clone(),
+                error,
+            })?;
+        }
+
+        match recorded.next() {
+            Some(choice) => Err(skipped(config, &choice.name)),
+            None => Ok(session),
+        }
+    }
+
+    pub fn to_xml_string(&self) -> Result<String, Error> {
+        spec::to_xml_string(self, "choices", &[])
+    }
+    pub fn write_to<W: io::Write>(&self, writer: W) -> Result<(), Error> {
+        spec::write_to(writer, &self.to_xml_string()?)
+    }
+}
+impl TryFrom<&str> for Choices {
+    type Error = Error;
+
+    fn try_from(string: &str) -> Result<Self, Self::Error> {
+        spec::from_str(string)
+    }
+}
+impl TryFrom<&[u8]> for Choices {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        spec::from_str(&spec::decode(bytes)?)
+    }
+}
+impl<T> TryFrom<BufReader<T>> for Choices
+where
+    T: io::Read,
+{
+    type Error = Error;
+
+    fn try_from(reader: BufReader<T>) -> Result<Self, Self::Error> {
+        Self::try_from(spec::read_all(reader)?.as_slice())
+    }
+}
+
+/// The error for choices of a step which was not visited.
+fn skipped(config: &Config, step: &str) -> ChoicesError {
+    if config.install_steps.list().iter().any(|s| s.name == step) {
+        ChoicesError::StepNotVisible(step.to_string())
+    } else {
+        ChoicesError::UnknownStep(step.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Choices, ChoicesError};
+    use crate::{Config, InstallerSession, MemoryEnvironment, SelectionError};
+
+    const XML: &str = r#"
+        <config>
+            <moduleName>Example Mod</moduleName>
+            <installSteps order="Explicit">
+                <installStep name="Options">
+                    <optionalFileGroups order="Explicit">
+                        <group name="Textures" type="SelectExactlyOne">
+                            <plugins order="Explicit">
+                                <plugin name="SD"><description/></plugin>
+                                <plugin name="HD">
+                                    <description/>
+                                    <conditionFlags><flag name="hd">On</flag></conditionFlags>
+                                </plugin>
+                            </plugins>
+                        </group>
+                        <group name="Extras" type="SelectAny">
+                            <plugins order="Explicit">
+                                <plugin name="Sounds"><description/></plugin>
+                                <plugin name="Music"><description/></plugin>
+                            </plugins>
+                        </group>
+                    </optionalFileGroups>
+                </installStep>
+                <installStep name="HD Options">
+                    <visible><flagDependency flag="hd" value="On"/></visible>
+                    <optionalFileGroups order="Explicit">
+                        <group name="Size" type="SelectAtMostOne">
+                            <plugins order="Explicit">
+                                <plugin name="2K"><description/></plugin>
+                                <plugin name="4K"><description/></plugin>
+                            </plugins>
+                        </group>
+                    </optionalFileGroups>
+                </installStep>
+            </installSteps>
+        </config>
+        "#;
+
+    fn choices(xml: &str) -> Choices {
+        Choices::try_from(xml).unwrap()
+    }
+
+    #[test]
+    pub fn record_and_replay() {
+        let config = Config::try_from(XML).unwrap();
+        let env = MemoryEnvironment::new();
+        let mut session = InstallerSession::new(&config, &env);
+        session.select(0, 1).unwrap();
+        session.select(1, 1).unwrap();
+        session.next().unwrap();
+        session.select(0, 1).unwrap();
+        session.next().unwrap();
+
+        let recorded = Choices::from_session(&session);
+        let xml = recorded.to_xml_string().unwrap();
+        assert_eq!(Choices::try_from(xml.as_str()).unwrap(), recorded);
+        assert_eq!(recorded.steps[0].groups[1].plugins[0].name, "Music");
+        assert!(recorded.steps[1].groups[0].plugins.len() == 1);
+
+        let replayed = recorded.replay(&config, &env).unwrap();
+        assert!(replayed.is_finished());
+        assert_eq!(replayed.install_plan(), session.install_plan());
+        assert_eq!(Choices::from_session(&replayed), recorded);
+    }
+
+    #[test]
+    pub fn incompatible_choices() {
+        let config = Config::try_from(XML).unwrap();
+        let env = MemoryEnvironment::new();
+        let replay = |xml: &str| choices(xml).replay(&config, &env).err().unwrap();
+
+        let sd = r#"<step name="Options"><group name="Textures"><plugin name="SD"/></group><group name="Extras"/></step>"#;
+        assert!(choices(&format!("<choices>{sd}</choices>"))
+            .replay(&config, &env)
+            .is_ok());
+
+        assert_eq!(
+            replay(&format!(
+                r#"<choices>{sd}<step name="HD Options"/></choices>"#
+            )),
+            ChoicesError::StepNotVisible("HD Options".to_string())
+        );
+        assert_eq!(
+            replay(&format!(r#"<choices>{sd}<step name="Other"/></choices>"#)),
+            ChoicesError::UnknownStep("Other".to_string())
+        );
+        assert_eq!(
+            replay("<choices/>"),
+            ChoicesError::MissingStep("Options".to_string())
+        );
+        assert_eq!(
+            replay(&format!(
+                "<choices>{}</choices>",
+                sd.replace(r#"<group name="Extras"/>"#, "")
+            )),
+            ChoicesError::MissingGroup {
+                step: "Options".to_string(),
+                group: "Extras".to_string()
+            }
+        );
+        assert_eq!(
+            replay(&format!(
+                "<choices>{}</choices>",
+                sd.replace("Extras", "Other")
+            )),
+            ChoicesError::UnknownGroup {
+                step: "Options".to_string(),
+                group: "Other".to_string()
+            }
+        );
+        assert_eq!(
+            replay(&format!(
+                "<choices>{}</choices>",
+                sd.replace(
+                    r#"<plugin name="SD"/>"#,
+                    r#"<plugin name="SD"/><plugin name="HD"/>"#
+                )
+            )),
+            ChoicesError::Selection {
+                step: "Options".to_string(),
+                error: SelectionError::ExactlyOne {
+                    group: "Textures".to_string(),
+                    selected: 2
+                }
+            }
+        );
+        assert!(matches!(
+            replay(&format!("<choices>{}</choices>", sd.replace("SD", "LD"))),
+            ChoicesError::Selection {
+                error: SelectionError::UnknownPlugin { .. },
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    pub fn duplicate_group_names() {
+        let xml = XML.replace(r#"<group name="Extras""#, r#"<group name="Textures""#);
+        let config = Config::try_from(xml.as_str()).unwrap();
+        let env = MemoryEnvironment::new();
+        let mut session = InstallerSession::new(&config, &env);
+        session.select(0, 0).unwrap();
+        session.select(1, 1).unwrap();
+        session.next().unwrap();
+
+        let recorded = Choices::from_session(&session);
+        let replayed = recorded.replay(&config, &env).unwrap();
+        assert_eq!(Choices::from_session(&replayed), recorded);
+        assert_eq!(replayed.install_plan(), session.install_plan());
+
+        let mut extra = recorded.clone();
+        let textures = extra.steps[0].groups[0].clone();
+        extra.steps[0].groups.push(textures);
+        assert_eq!(
+            extra.replay(&config, &env).err().unwrap(),
+            ChoicesError::UnknownGroup {
+                step: "Options".to_string(),
+                group: "Textures".to_string()
+            }
+        );
+    }
+}
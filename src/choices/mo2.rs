@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+
+use crate::{Choices, Error, GroupChoices, PluginName, StepChoices};
+
+/// The `meta.ini` section Mod Organizer 2 keeps the choices of a fomod in.
+const SECTION: &str = "installerFomod";
+
+impl Choices {
+    /// Reads the choices from the `[installerFomod]` section of a Mod
+    /// Organizer 2 `meta.ini`.
+    ///
+    /// The section holds nested arrays in the QSettings layout, e.g.
+    /// `steps\1\groups\2\plugins\1\name=HD`.
+    pub fn from_mo2_meta(ini: &str) -> Result<Self, Error> {
+        let mut values = HashMap::new();
+        let mut found = false;
+        let mut current = false;
+        for line in ini.lines().map(str::trim) {
+            if let Some(section) = section(line) {
+                current = section == SECTION;
+                found |= current;
+            } else if let Some((key, value)) = line.split_once('=').filter(|_| current) {
+                values.insert(key.trim().to_string(), unquote(value));
+            }
+        }
+        if !found {
+            return Err(import(format!("no [{SECTION}] section")));
+        }
+
+        let array = |prefix: &str| -> Result<Vec<String>, Error> {
+            let len = match values.get(&format!("{prefix}size")) {
+                Some(size) => size
+                    .parse::<usize>()
+                    .map_err(|_| import(format!("{prefix}size is not a number")))?,
+                None => 0,
+            };
+            Ok((1..=len).map(|idx| format!("{prefix}{idx}\\")).collect())
+        };
+        let name = |prefix: &str| {
+            values
+                .get(&format!("{prefix}name"))
+                .cloned()
+                .ok_or_else(|| import(format!("{prefix}name is missing")))
+        };
+
+        let mut steps = Vec::new();
+        for step in array("steps\\")? {
+            let mut groups = Vec::new();
+            for group in array(&format!("{step}groups\\"))? {
+                let plugins = array(&format!("{group}plugins\\"))?
+                    .iter()
+                    .map(|plugin| {
+                        Ok(PluginName {
+                            name: name(plugin)?,
+                        })
+                    })
+                    .collect::<Result<_, Error>>()?;
+                groups.push(GroupChoices {
+                    name: name(&group)?,
+                    plugins,
+                });
+            }
+            steps.push(StepChoices {
+                name: name(&step)?,
+                groups,
+            });
+        }
+        Ok(Self { steps })
+    }
+
+    /// Writes the choices into a Mod Organizer 2 `meta.ini`, replacing the
+    /// `[installerFomod]` section and keeping the others as they are.
+    pub fn to_mo2_meta(&self, ini: &str) -> String {
+        let newline = if ini.contains("\r\n") { "\r\n" } else { "\n" };
+
+        let mut entries = vec![format!("[{SECTION}]")];
+        let mut push = |key: String, value: &str| entries.push(format!("{key}={}", quote(value)));
+        push("steps\\size".to_string(), &self.steps.len().to_string());
+        for (sidx, step) in self.steps.iter().enumerate() {
+            let step_key = format!("steps\\{}\\", sidx + 1);
+            push(format!("{step_key}name"), &step.name);
+            push(
+                format!("{step_key}groups\\size"),
+                &step.groups.len().to_string(),
+            );
+            for (gidx, group) in step.groups.iter().enumerate() {
+                let group_key = format!("{step_key}groups\\{}\\", gidx + 1);
+                push(format!("{group_key}name"), &group.name);
+                push(
+                    format!("{group_key}plugins\\size"),
+                    &group.plugins.len().to_string(),
+                );
+                for (pidx, plugin) in group.plugins.iter().enumerate() {
+                    push(
+                        format!("{group_key}plugins\\{}\\name", pidx + 1),
+                        &plugin.name,
+                    );
+                }
+            }
+        }
+
+        let mut lines = Vec::new();
+        let mut replaced = false;
+        let mut skipping = false;
+        for line in ini.lines() {
+            if let Some(section) = section(line.trim()) {
+                skipping = section == SECTION;
+                if skipping && !replaced {
+                    lines.append(&mut entries);
+                    lines.push(String::new());
+                    replaced = true;
+                }
+            }
+            if !skipping {
+                lines.push(line.to_string());
+            }
+        }
+        while lines.last().is_some_and(|l| l.trim().is_empty()) {
+            lines.pop();
+        }
+        if !replaced {
+            if !lines.is_empty() {
+                lines.push(String::new());
+            }
+            lines.append(&mut entries);
+        }
+
+        let mut ini = lines.join(newline);
+        ini.push_str(newline);
+        ini
+    }
+}
+
+fn section(line: &str) -> Option<&str> {
+    line.strip_prefix('[')?.strip_suffix(']')
+}
+
+fn import(message: String) -> Error {
+    Error::Import {
+        format: "meta.ini",
+        message,
+    }
+}
+
+/// Reads a QSettings value, which may be quoted and contain escapes.
+fn unquote(value: &str) -> String {
+    let mut unquoted = String::new();
+    let mut chars = value.trim().chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {}
+            '\\' => match chars.next() {
+                Some('n') => unquoted.push('\n'),
+                Some('r') => unquoted.push('\r'),
+                Some('t') => unquoted.push('\t'),
+                Some(c) => unquoted.push(c),
+                None => {}
+            },
+            c => unquoted.push(c),
+        }
+    }
+    unquoted
+}
+
+/// Writes a value the way QSettings does, quoting it where it would be
+/// read back differently otherwise.
+fn quote(value: &str) -> String {
+    let escaped = value.replace('\\', "\\\\").replace('"', "\\\"");
+    if value.trim() != value || value.contains([',', ';', '=', '"']) {
+        format!("\"{escaped}\"")
+    } else {
+        escaped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Choices, Error};
+
+    const META: &str = "[General]\r\n\
+        gameName=SkyrimSE\r\n\
+        \r\n\
+        [installerFomod]\r\n\
+        steps\\size=1\r\n\
+        steps\\1\\name=Options\r\n\
+        steps\\1\\groups\\size=2\r\n\
+        steps\\1\\groups\\1\\name=Textures\r\n\
+        steps\\1\\groups\\1\\plugins\\size=1\r\n\
+        steps\\1\\groups\\1\\plugins\\1\\name=HD\r\n\
+        steps\\1\\groups\\2\\name=\"Extras; Misc\"\r\n\
+        steps\\1\\groups\\2\\plugins\\size=0\r\n\
+        \r\n\
+        [installedFiles]\r\n\
+        size=0\r\n";
+
+    #[test]
+    pub fn mo2_meta() {
+        let choices = Choices::from_mo2_meta(META).unwrap();
+        assert_eq!(choices.steps[0].name, "Options");
+        assert_eq!(choices.steps[0].groups[0].plugins[0].name, "HD");
+        assert_eq!(choices.steps[0].groups[1].name, "Extras; Misc");
+        assert!(choices.steps[0].groups[1].plugins.is_empty());
+
+        let written = choices.to_mo2_meta(META);
+        assert_eq!(written, META);
+        assert_eq!(Choices::from_mo2_meta(&written).unwrap(), choices);
+
+        let added = choices.to_mo2_meta("[General]\ngameName=SkyrimSE\n");
+        assert!(added.starts_with("[General]\ngameName=SkyrimSE\n\n[installerFomod]\n"));
+        assert_eq!(Choices::from_mo2_meta(&added).unwrap(), choices);
+
+        assert!(matches!(
+            Choices::from_mo2_meta("[General]\n"),
+            Err(Error::Import { .. })
+        ));
+        assert!(Choices::from_mo2_meta(&META.replace("groups\\size=2", "groups\\size=3")).is_err());
+    }
+}
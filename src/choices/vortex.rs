@@ -0,0 +1,242 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{Choices, ChoicesError, Config, Error, GroupChoices, PluginName, StepChoices};
+
+/// The `installerChoices` attribute Vortex keeps on an installed mod.
+#[derive(Deserialize, Serialize)]
+struct InstallerChoices {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    options: Vec<Step>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Step {
+    name: String,
+    #[serde(default)]
+    groups: Vec<Group>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Group {
+    name: String,
+    #[serde(default)]
+    choices: Vec<Choice>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Choice {
+    name: String,
+    /// Index into the plugins of the group, in display order.
+    idx: usize,
+}
+
+impl Choices {
+    /// Reads the `installerChoices` of a Vortex mod, either on its own or as
+    /// part of the attributes of the mod.
+    pub fn from_vortex_json(json: &str) -> Result<Self, Error> {
+        let mut value = serde_json::from_str::<Value>(json).map_err(import)?;
+        if let Some(choices) = value.get_mut("installerChoices") {
+            value = choices.take();
+        }
+        let choices = serde_json::from_value::<InstallerChoices>(value).map_err(import)?;
+        if choices.kind != "fomod" {
+            return Err(Error::Import {
+                format: "installerChoices",
+                message: format!("unsupported installer type '{}'", choices.kind),
+            });
+        }
+
+        let steps = choices
+            .options
+            .into_iter()
+            .map(|step| StepChoices {
+                name: step.name,
+                groups: step
+                    .groups
+                    .into_iter()
+                    .map(|group| GroupChoices {
+                        name: group.name,
+                        plugins: group
+                            .choices
+                            .into_iter()
+                            .map(|choice| PluginName { name: choice.name })
+                            .collect(),
+                    })
+                    .collect(),
+            })
+            .collect();
+        Ok(Self { steps })
+    }
+
+    /// Writes the choices as Vortex `installerChoices`.
+    ///
+    /// Vortex records the index of every plugin as well, which is looked up
+    /// in `config`. As in `replay`, the n-th choices of a name are those of
+    /// the n-th step or group of that name.
+    pub fn to_vortex_json(&self, config: &Config) -> Result<String, ChoicesError> {
+        let mut options = Vec::new();
+        for (i, step) in self.steps.iter().enumerate() {
+            let same_name = self.steps[..i]
+                .iter()
+                .filter(|s| s.name == step.name)
+                .count();
+            let config_step = config
+                .install_steps
+                .sorted()
+                .into_iter()
+                .filter(|s| s.name == step.name)
+                .nth(same_name)
+                .ok_or_else(|| ChoicesError::UnknownStep(step.name.clone()))?;
+
+            let mut groups = Vec::new();
+            for (j, group) in step.groups.iter().enumerate() {
+                let same_name = step.groups[..j]
+                    .iter()
+                    .filter(|g| g.name == group.name)
+                    .count();
+                let config_group = config_step
+                    .optional_file_groups
+                    .sorted()
+                    .into_iter()
+                    .filter(|g| g.name == group.name)
+                    .nth(same_name)
+                    .ok_or_else(|| ChoicesError::UnknownGroup {
+                        step: step.name.clone(),
+                        group: group.name.clone(),
+                    })?;
+                let names = group
+                    .plugins
+                    .iter()
+                    .map(|p| p.name.as_str())
+                    .collect::<Vec<_>>();
+                let indices = config_group.plugin_indices(&names).map_err(|error| {
+                    ChoicesError::Selection {
+                        step: step.name.clone(),
+                        error,
+                    }
+                })?;
+                groups.push(Group {
+                    name: group.name.clone(),
+                    choices: names
+                        .iter()
+                        .zip(indices)
+                        .map(|(name, idx)| Choice {
+                            name: name.to_string(),
+                            idx,
+                        })
+                        .collect(),
+                });
+            }
+            options.push(Step {
+                name: step.name.clone(),
+                groups,
+            });
+        }
+
+        let choices = InstallerChoices {
+            kind: "fomod".to_string(),
+            options,
+        };
+        Ok(serde_json::to_string(&choices).expect("choices serialize to json"))
+    }
+}
+
+fn import(e: serde_json::Error) -> Error {
+    Error::Import {
+        format: "installerChoices",
+        message: e.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Choices, ChoicesError, Config, MemoryEnvironment};
+
+    const XML: &str = r#"
+        <config>
+            <moduleName>Example Mod</moduleName>
+            <installSteps order="Explicit">
+                <installStep name="Options">
+                    <optionalFileGroups order="Explicit">
+                        <group name="Textures" type="SelectExactlyOne">
+                            <plugins order="Ascending">
+                                <plugin name="SD"><description/></plugin>
+                                <plugin name="HD"><description/></plugin>
+                            </plugins>
+                        </group>
+                    </optionalFileGroups>
+                </installStep>
+            </installSteps>
+        </config>
+        "#;
+
+    #[test]
+    pub fn vortex_json() {
+        let config = Config::try_from(XML).unwrap();
+        let json = r#"{
+            "installerChoices": {
+                "type": "fomod",
+                "options": [{
+                    "name": "Options",
+                    "groups": [{"name": "Textures", "choices": [{"name": "SD", "idx": 1}]}]
+                }]
+            }
+        }"#;
+
+        let choices = Choices::from_vortex_json(json).unwrap();
+        assert_eq!(choices.steps[0].groups[0].plugins[0].name, "SD");
+        assert!(choices
+            .replay(&config, &MemoryEnvironment::new())
+            .unwrap()
+            .is_finished());
+
+        let written = choices.to_vortex_json(&config).unwrap();
+        assert_eq!(
+            written,
+            r#"{"type":"fomod","options":[{"name":"Options","groups":[{"name":"Textures","choices":[{"name":"SD","idx":1}]}]}]}"#
+        );
+        assert_eq!(Choices::from_vortex_json(&written).unwrap(), choices);
+
+        assert!(Choices::from_vortex_json(r#"{"type":"bain","options":[]}"#).is_err());
+        assert!(Choices::from_vortex_json("{").is_err());
+        assert_eq!(
+            Choices::from_vortex_json(&written.replace("Options", "Other"))
+                .unwrap()
+                .to_vortex_json(&config),
+            Err(ChoicesError::UnknownStep("Other".to_string()))
+        );
+    }
+
+    #[test]
+    pub fn duplicate_group_names() {
+        let xml = XML.replace(
+            "</group>",
+            r#"</group>
+                        <group name="Textures" type="SelectExactlyOne">
+                            <plugins order="Explicit">
+                                <plugin name="Low"><description/></plugin>
+                                <plugin name="High"><description/></plugin>
+                            </plugins>
+                        </group>"#,
+        );
+        let config = Config::try_from(xml.as_str()).unwrap();
+        let json = r#"{"type":"fomod","options":[{"name":"Options","groups":[{"name":"Textures","choices":[{"name":"SD","idx":1}]},{"name":"Textures","choices":[{"name":"High","idx":1}]}]}]}"#;
+
+        let choices = Choices::from_vortex_json(json).unwrap();
+        assert_eq!(choices.to_vortex_json(&config).unwrap(), json);
+
+        let mut extra = choices.clone();
+        let textures = extra.steps[0].groups[0].clone();
+        extra.steps[0].groups.push(textures);
+        assert_eq!(
+            extra.to_vortex_json(&config),
+            Err(ChoicesError::UnknownGroup {
+                step: "Options".to_string(),
+                group: "Textures".to_string()
+            })
+        );
+    }
+}
@@ -0,0 +1,554 @@
+use std::collections::HashMap;
+
+use crate::{
+    Choices, Config, Flags, GameEnvironment, Group, GroupType, InstallPlan, InstallerSession,
+    PluginTypeEnum,
+};
+
+/// An installation outcome, with every combination of choices leading to it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Outcome {
+    pub plan: InstallPlan,
+    pub choices: Vec<Choices>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Enumeration {
+    pub outcomes: Vec<Outcome>,
+    /// The number of valid combinations which were tried.
+    pub combinations: usize,
+    /// False when the limit was reached before every combination was tried.
+    pub complete: bool,
+}
+
+/// Walks every valid combination of selections through the steps.
+///
+/// Every group takes each selection allowed by its type and the types of its
+/// plugins, and steps are visited as their visibility decides. Outcomes with
+/// the same files are merged unless duplicates are kept.
+pub struct Enumerator<'a, E: GameEnvironment + ?Sized> {
+    config: &'a Config,
+    env: &'a E,
+    limit: Option<usize>,
+    duplicates: bool,
+}
+impl<'a, E: GameEnvironment + ?Sized> Enumerator<'a, E> {
+    pub fn new(config: &'a Config, env: &'a E) -> Self {
+        Self {
+            config,
+            env,
+            limit: None,
+            duplicates: false,
+        }
+    }
+    /// Stops after trying `limit` selections of groups, which bounds the work
+    /// for configs with too many combinations to walk.
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+    /// Keeps one outcome per combination, even if the files are the same.
+    pub fn with_duplicates(mut self, duplicates: bool) -> Self {
+        self.duplicates = duplicates;
+        self
+    }
+
+    pub fn enumerate(&self) -> Enumeration {
+        let mut enumeration = Enumeration::default();
+        // Outcomes by what they install.
+        let mut outcomes = HashMap::<_, usize>::new();
+        let mut budget = Budget::new(self.limit);
+        let mut session = InstallerSession::new(self.config, self.env);
+        walk(&mut session, self.env, &mut budget, &mut |session| {
+            enumeration.combinations += 1;
+
+            let plan = session.install_plan();
+            let choices = Choices::from_session(session);
+            let existing = (!self.duplicates)
+                .then(|| outcomes.get(&files(&plan)).copied())
+                .flatten();
+            match existing {
+                Some(idx) => enumeration.outcomes[idx].choices.push(choices),
+                None => {
+                    outcomes.insert(files(&plan), enumeration.outcomes.len());
+                    enumeration.outcomes.push(Outcome {
+                        plan,
+                        choices: vec![choices],
+                    });
+                }
+            }
+            true
+        });
+        enumeration.complete = !budget.exceeded;
+        enumeration
+    }
+}
+
+/// The number of group selections a walk may try.
+pub(crate) struct Budget {
+    limit: Option<usize>,
+    spent: usize,
+    /// Whether a selection was left untried because of the limit.
+    pub(crate) exceeded: bool,
+}
+impl Budget {
+    pub(crate) fn new(limit: Option<usize>) -> Self {
+        Self {
+            limit,
+            spent: 0,
+            exceeded: false,
+        }
+    }
+    fn spend(&mut self) -> bool {
+        if self.limit == Some(self.spent) {
+            self.exceeded = true;
+            return false;
+        }
+        self.spent += 1;
+        true
+    }
+}
+
+/// Calls `visit` with the finished session for every valid combination of
+/// selections from the current step on, until it returns false or the
+/// budget runs out.
+///
+/// Returns false if the walk was stopped; the session is left as it was.
+pub(crate) fn walk<'a, E: GameEnvironment + ?Sized>(
+    session: &mut InstallerSession<'a, E>,
+    env: &E,
+    budget: &mut Budget,
+    visit: &mut dyn FnMut(&InstallerSession<'a, E>) -> bool,
+) -> bool {
+    if session.is_finished() {
+        return visit(session);
+    }
+    let groups = session.groups();
+    let flags = session.step_flags();
+    select(session, env, &groups, &flags, budget, visit)
+}
+
+/// Tries every selection of the groups from `groups[0]` on.
+fn select<'a, E: GameEnvironment + ?Sized>(
+    session: &mut InstallerSession<'a, E>,
+    env: &E,
+    groups: &[&Group],
+    flags: &Flags,
+    budget: &mut Budget,
+    visit: &mut dyn FnMut(&InstallerSession<'a, E>) -> bool,
+) -> bool {
+    let idx = session.groups().len() - groups.len();
+    let Some((group, rest)) = groups.split_first() else {
+        if session.next().is_err() {
+            return true;
+        }
+        let more = walk(session, env, budget, visit);
+        session.previous();
+        return more;
+    };
+
+    let previous = session.selected(idx).to_vec();
+    let mut more = true;
+    for selection in selections(group, env, flags) {
+        if !budget.spend() {
+            more = false;
+            break;
+        }
+        if group.validate_selection(&selection, env, flags).is_ok()
+            && session.set_selected(idx, &selection).is_ok()
+            && !select(session, env, rest, flags, budget, visit)
+        {
+            more = false;
+            break;
+        }
+    }
+    // The indices were taken from the same group.
+    let _ = session.set_selected(idx, &previous);
+    more
+}
+
+/// What an outcome installs, whichever plugins the files came from.
+fn files(plan: &InstallPlan) -> Vec<(bool, String, String)> {
+    let mut files = plan
+        .effective_files()
+        .into_iter()
+        .map(|pf| {
+            let destination = pf.destination().unwrap_or_default().to_lowercase();
+            (
+                pf.is_folder(),
+                pf.source().replace('\\', "/").to_lowercase(),
+                destination,
+            )
+        })
+        .collect::<Vec<_>>();
+    files.sort();
+    files.dedup();
+    files
+}
+
+/// The selections a group allows, as indices into the sorted plugins.
+///
+/// `Required` plugins are always selected and `NotUsable` ones never are, so
+/// only the other plugins are combined; subsets are built one at a time, as
+/// there can be far too many to collect.
+fn selections<E: GameEnvironment + ?Sized>(
+    group: &Group,
+    env: &E,
+    flags: &Flags,
+) -> Box<dyn Iterator<Item = Vec<usize>>> {
+    let mut required = Vec::new();
+    let mut free = Vec::new();
+    for (idx, plugin) in group.plugins.inner().sorted().into_iter().enumerate() {
+        match plugin.plugin_type(env, flags) {
+            PluginTypeEnum::Required => required.push(idx),
+            PluginTypeEnum::NotUsable => {}
+            _ => free.push(idx),
+        }
+    }
+
+    match group.plugins {
+        GroupType::SelectExactlyOne(_) | GroupType::SelectAtMostOne(_) if !required.is_empty() => {
+            Box::new((required.len() == 1).then_some(required).into_iter())
+        }
+        GroupType::SelectExactlyOne(_) => Box::new(free.into_iter().map(|idx| vec![idx])),
+        GroupType::SelectAtMostOne(_) => Box::new(
+            [vec![]]
+                .into_iter()
+                .chain(free.into_iter().map(|idx| vec![idx])),
+        ),
+        GroupType::SelectAll(_) => {
+            required.extend(free);
+            required.sort_unstable();
+            Box::new([required].into_iter())
+        }
+        GroupType::SelectAtLeastOne(_) | GroupType::SelectAny(_) => {
+            let skip_empty =
+                required.is_empty() && matches!(group.plugins, GroupType::SelectAtLeastOne(_));
+            Box::new(Subsets::new(required, free).skip(usize::from(skip_empty)))
+        }
+    }
+}
+
+/// Every subset of `free`, each joined with `required`, counting up in binary.
+struct Subsets {
+    required: Vec<usize>,
+    free: Vec<usize>,
+    /// Which free plugins the next subset holds; `None` once all were made.
+    included: Option<Vec<bool>>,
+}
+impl Subsets {
+    fn new(required: Vec<usize>, free: Vec<usize>) -> Self {
+        let included = Some(vec![false; free.len()]);
+        Self {
+            required,
+            free,
+            included,
+        }
+    }
+}
+impl Iterator for Subsets {
+    type Item = Vec<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let included = self.included.as_mut()?;
+        let mut subset = self.required.clone();
+        subset.extend(
+            self.free
+                .iter()
+                .zip(included.iter())
+                .filter(|(_, &inc)| inc)
+                .map(|(&idx, _)| idx),
+        );
+        subset.sort_unstable();
+
+        match included.iter().position(|inc| !inc) {
+            Some(zero) => {
+                included[..zero].fill(false);
+                included[zero] = true;
+            }
+            None => self.included = None,
+        }
+        Some(subset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Enumerator;
+    use crate::{Config, MemoryEnvironment};
+
+    const XML: &str = r#"
+        <config>
+            <moduleName>Example Mod</moduleName>
+            <installSteps order="Explicit">
+                <installStep name="Options">
+                    <optionalFileGroups order="Explicit">
+                        <group name="Textures" type="SelectAtMostOne">
+                            <plugins order="Explicit">
+                                <plugin name="HD">
+                                    <description/>
+                                    <files><folder source="hd" destination="textures"/></files>
+                                    <conditionFlags><flag name="hd">On</flag></conditionFlags>
+                                </plugin>
+                                <plugin name="Broken">
+                                    <description/>
+                                    <files><folder source="broken" destination="textures"/></files>
+                                    <typeDescriptor><type name="NotUsable"/></typeDescriptor>
+                                </plugin>
+                            </plugins>
+                        </group>
+                        <group name="Readme" type="SelectAny">
+                            <plugins order="Explicit">
+                                <plugin name="Readme"><description/></plugin>
+                            </plugins>
+                        </group>
+                    </optionalFileGroups>
+                </installStep>
+                <installStep name="HD Options">
+                    <visible><flagDependency flag="hd" value="On"/></visible>
+                    <optionalFileGroups order="Explicit">
+                        <group name="Size" type="SelectExactlyOne">
+                            <plugins order="Explicit">
+                                <plugin name="2K">
+                                    <description/>
+                                    <files><file source="2k.ini" destination="hd.ini"/></files>
+                                </plugin>
+                                <plugin name="4K">
+                                    <description/>
+                                    <files><file source="4k.ini" destination="HD.ini"/></files>
+                                </plugin>
+                            </plugins>
+                        </group>
+                    </optionalFileGroups>
+                </installStep>
+            </installSteps>
+        </config>
+        "#;
+
+    /// Two single choice groups, and a pattern for each combination.
+    const MATRIX: &str = r#"
+        <config xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance"
+            xsi:noNamespaceSchemaLocation="http://qconsulting.ca/fo3/ModConfig5.0.xsd">
+
+            <moduleName>Example Mod</moduleName>
+
+            <moduleDependencies operator="And">
+                <fileDependency file="depend1.plugin" state="Active"/>
+                <dependencies operator="Or">
+                    <fileDependency file="depend2v1.plugin" state="Active"/>
+                    <fileDependency file="depend2v2.plugin" state="Active"/>
+                </dependencies>
+            </moduleDependencies>
+
+            <installSteps order="Explicit">
+                <installStep name="Choose Option">
+                    <optionalFileGroups order="Explicit">
+
+                        <group name="Select an option:" type="SelectExactlyOne">
+                            <plugins order="Explicit">
+
+                                <plugin name="Option A">
+                                    <description>Select this to install Option A!</description>
+                                    <image path="fomod/option_a.png"/>
+                                    <conditionFlags>
+                                        <flag name="option_a">selected</flag>
+                                    </conditionFlags>
+                                    <typeDescriptor>
+                                        <type name="Recommended"/>
+                                    </typeDescriptor>
+                                </plugin>
+
+                                <plugin name="Option B">
+                                    <description>Select this to install Option B!</description>
+                                    <image path="fomod/option_b.png"/>
+                                    <conditionFlags>
+                                        <flag name="option_b">selected</flag>
+                                    </conditionFlags>
+                                    <typeDescriptor>
+                                        <type name="Optional"/>
+                                    </typeDescriptor>
+                                </plugin>
+
+                            </plugins>
+                        </group>
+
+                        <group name="Select a texture:" type="SelectExactlyOne">
+                            <plugins order="Explicit">
+
+                                <plugin name="Texture Blue">
+                                    <description>Select this to install Texture Blue!</description>
+                                    <image path="fomod/texture_blue.png"/>
+                                    <conditionFlags>
+                                        <flag name="texture_blue">selected</flag>
+                                    </conditionFlags>
+                                    <typeDescriptor>
+                                        <type name="Optional"/>
+                                    </typeDescriptor>
+                                </plugin>
+
+                                <plugin name="Texture Red">
+                                    <description>Select this to install Texture Red!</description>
+                                    <image path="fomod/texture_red.png"/>
+                                    <conditionFlags>
+                                        <flag name="texture_red">selected</flag>
+                                    </conditionFlags>
+                                    <typeDescriptor>
+                                        <type name="Optional"/>
+                                    </typeDescriptor>
+                                </plugin>
+
+                            </plugins>
+                        </group>
+
+                    </optionalFileGroups>
+                </installStep>
+            </installSteps>
+
+            <conditionalFileInstalls>
+                <patterns>
+                    <pattern>
+                        <dependencies operator="And">
+                            <flagDependency flag="option_a" value="selected"/>
+                            <flagDependency flag="texture_blue" value="selected"/>
+                        </dependencies>
+                        <files>
+                            <folder source="option_a"/>
+                            <folder source="texture_blue_a"/>
+                        </files>
+                    </pattern>
+                    <pattern>
+                        <dependencies operator="And">
+                            <flagDependency flag="option_a" value="selected"/>
+                            <flagDependency flag="texture_red" value="selected"/>
+                        </dependencies>
+                        <files>
+                            <folder source="option_a"/>
+                            <folder source="texture_red_a"/>
+                        </files>
+                    </pattern>
+                    <pattern>
+                        <dependencies operator="And">
+                            <flagDependency flag="option_b" value="selected"/>
+                            <flagDependency flag="texture_blue" value="selected"/>
+                        </dependencies>
+                        <files>
+                            <folder source="option_b"/>
+                            <folder source="texture_blue_b"/>
+                        </files>
+                    </pattern>
+                    <pattern>
+                        <dependencies operator="And">
+                            <flagDependency flag="option_b" value="selected"/>
+                            <flagDependency flag="texture_red" value="selected"/>
+                        </dependencies>
+                        <files>
+                            <folder source="option_b"/>
+                            <folder source="texture_red_b"/>
+                        </files>
+                    </pattern>
+                </patterns>
+            </conditionalFileInstalls>
+
+        </config>
+        "#;
+
+    #[test]
+    pub fn enumeration() {
+        let config = Config::try_from(XML).unwrap();
+        let env = MemoryEnvironment::new();
+
+        // No texture, or HD with either size; with or without the readme.
+        let all = Enumerator::new(&config, &env).enumerate();
+        assert!(all.complete);
+        assert_eq!(all.combinations, 6);
+        assert_eq!(all.outcomes.len(), 3);
+        assert!(all.outcomes.iter().all(|o| o.choices.len() == 2));
+        assert!(all.outcomes[0].plan.files.is_empty());
+        assert_eq!(all.outcomes[2].plan.files[1].source(), "4k.ini");
+        assert_eq!(all.outcomes[2].choices[1].steps.len(), 2);
+
+        let duplicates = Enumerator::new(&config, &env)
+            .with_duplicates(true)
+            .enumerate();
+        assert_eq!(duplicates.outcomes.len(), 6);
+
+        // Broken is never tried; the readme is tried for each texture and size.
+        let limited = Enumerator::new(&config, &env).with_limit(3).enumerate();
+        assert!(!limited.complete);
+        assert_eq!(limited.combinations, 2);
+        assert_eq!(
+            Enumerator::new(&config, &env).with_limit(10).enumerate(),
+            all
+        );
+    }
+
+    #[test]
+    pub fn large_groups() {
+        let plugins = (0..200)
+            .map(|i| format!(r#"<plugin name="P{i:03}"><description/></plugin>"#))
+            .collect::<String>();
+        let xml = format!(
+            r#"
+            <config>
+                <moduleName>Example Mod</moduleName>
+                <installSteps order="Explicit">
+                    <installStep name="Options">
+                        <optionalFileGroups order="Explicit">
+                            <group name="All" type="SelectAll">
+                                <plugins order="Explicit">{plugins}</plugins>
+                            </group>
+                            <group name="Any" type="SelectAny">
+                                <plugins order="Explicit">{plugins}</plugins>
+                            </group>
+                        </optionalFileGroups>
+                    </installStep>
+                </installSteps>
+            </config>
+            "#
+        );
+        let config = Config::try_from(xml.as_str()).unwrap();
+        let env = MemoryEnvironment::new();
+
+        let limited = Enumerator::new(&config, &env).with_limit(100).enumerate();
+        assert!(!limited.complete);
+        assert_eq!(limited.combinations, 99);
+        let choices = &limited.outcomes[0].choices[98].steps[0].groups;
+        assert_eq!(choices[0].plugins.len(), 200);
+        // The 98th subset of the second group, counting up in binary.
+        let any = choices[1]
+            .plugins
+            .iter()
+            .map(|p| p.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(any, vec!["P001", "P005", "P006"]);
+    }
+
+    #[test]
+    pub fn install_matrix() {
+        let config = Config::try_from(MATRIX).unwrap();
+        let env = MemoryEnvironment::new();
+
+        let enumeration = Enumerator::new(&config, &env).enumerate();
+        assert!(enumeration.complete);
+        let outcomes = enumeration
+            .outcomes
+            .iter()
+            .map(|o| {
+                o.plan
+                    .effective_files()
+                    .iter()
+                    .map(|f| f.source())
+                    .collect()
+            })
+            .collect::<Vec<Vec<_>>>();
+        assert_eq!(
+            outcomes,
+            vec![
+                vec!["option_a", "texture_blue_a"],
+                vec!["option_a", "texture_red_a"],
+                vec!["option_b", "texture_blue_b"],
+                vec!["option_b", "texture_red_b"],
+            ]
+        );
+    }
+}
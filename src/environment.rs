@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+
+use crate::DependencyState;
+
+/// Answers the questions a fomod asks about the game it is installed into.
+///
+/// This is consulted for `fileDependency`, `gameDependency` and `fommDependency`.
+pub trait GameEnvironment {
+    fn file_state(&self, file_name: &str) -> DependencyState;
+    /// `None` when the version is unknown, which fails every `gameDependency`.
+    fn game_version(&self) -> Option<&str>;
+    /// The version of the installer, `None` fails every `fommDependency`.
+    fn fomm_version(&self) -> Option<&str>;
+}
+impl<T: GameEnvironment + ?Sized> GameEnvironment for &T {
+    fn file_state(&self, file_name: &str) -> DependencyState {
+        (**self).file_state(file_name)
+    }
+    fn game_version(&self) -> Option<&str> {
+        (**self).game_version()
+    }
+    fn fomm_version(&self) -> Option<&str> {
+        (**self).fomm_version()
+    }
+}
+
+/// A `GameEnvironment` kept entirely in memory.
+///
+/// File names are matched case-insensitively and regardless of path separator;
+/// files which have not been added are reported as `Missing`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemoryEnvironment {
+    files: HashMap<String, DependencyState>,
+    game_version: Option<String>,
+    fomm_version: Option<String>,
+}
+impl MemoryEnvironment {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn with_file(mut self, file_name: &str, state: DependencyState) -> Self {
+        self.set_file(file_name, state);
+        self
+    }
+    pub fn with_game_version(mut self, version: &str) -> Self {
+        self.set_game_version(Some(version));
+        self
+    }
+    pub fn with_fomm_version(mut self, version: &str) -> Self {
+        self.set_fomm_version(Some(version));
+        self
+    }
+    pub fn set_file(&mut self, file_name: &str, state: DependencyState) {
+        self.files.insert(normalize_file_name(file_name), state);
+    }
+    pub fn remove_file(&mut self, file_name: &str) {
+        self.files.remove(&normalize_file_name(file_name));
+    }
+    pub fn set_game_version(&mut self, version: Option<&str>) {
+        self.game_version = version.map(str::to_string);
+    }
+    pub fn set_fomm_version(&mut self, version: Option<&str>) {
+        self.fomm_version = version.map(str::to_string);
+    }
+}
+impl GameEnvironment for MemoryEnvironment {
+    fn file_state(&self, file_name: &str) -> DependencyState {
+        self.files
+            .get(&normalize_file_name(file_name))
+            .copied()
+            .unwrap_or(DependencyState::Missing)
+    }
+    fn game_version(&self) -> Option<&str> {
+        self.game_version.as_deref()
+    }
+    fn fomm_version(&self) -> Option<&str> {
+        self.fomm_version.as_deref()
+    }
+}
+
+fn normalize_file_name(file_name: &str) -> String {
+    file_name.replace('\\', "/").to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GameEnvironment, MemoryEnvironment};
+    use crate::{Config, DependencyState, Flags};
+
+    #[test]
+    pub fn file_states() {
+        let mut env = MemoryEnvironment::new()
+            .with_file("Data\\Example.esp", DependencyState::Active)
+            .with_file("other.esp", DependencyState::Inactive);
+
+        assert_eq!(env.file_state("data/example.ESP"), DependencyState::Active);
+        assert_eq!(env.file_state("other.esp"), DependencyState::Inactive);
+        assert_eq!(env.file_state("unknown.esp"), DependencyState::Missing);
+
+        env.remove_file("other.esp");
+        assert_eq!(env.file_state("other.esp"), DependencyState::Missing);
+    }
+
+    #[test]
+    pub fn conditional_installs() {
+        let xml = r#"
+        <config xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance"
+            xsi:noNamespaceSchemaLocation="http://qconsulting.ca/fo3/ModConfig5.0.xsd">
+
+            <moduleName>Example Mod</moduleName>
+
+            <conditionalFileInstalls>
+                <patterns>
+                    <pattern>
+                        <dependencies operator="Or">
+                            <fileDependency file="Other Mod.esp" state="Active"/>
+                            <gameDependency version="1.9"/>
+                        </dependencies>
+                        <files>
+                            <file source="patch.esp"/>
+                        </files>
+                    </pattern>
+                    <pattern>
+                        <dependencies operator="And">
+                            <fileDependency file="Other Mod.esp" state="Missing"/>
+                            <fommDependency version="0.13.21"/>
+                        </dependencies>
+                        <files>
+                            <file source="standalone.esp"/>
+                        </files>
+                    </pattern>
+                </patterns>
+            </conditionalFileInstalls>
+        </config>
+        "#;
+
+        let config = Config::try_from(xml).unwrap();
+        let patch = &config.conditional_file_installs[0].dependencies;
+        let standalone = &config.conditional_file_installs[1].dependencies;
+        let flags = Flags::new();
+
+        let mut env = MemoryEnvironment::new().with_game_version("1.8");
+        assert!(!patch.evaluate(&env, &flags));
+        assert!(!standalone.evaluate(&env, &flags));
+
+        env.set_fomm_version(Some("0.13.21"));
+        assert!(standalone.evaluate(&env, &flags));
+
+        env.set_file("other mod.esp", DependencyState::Inactive);
+        assert!(!patch.evaluate(&env, &flags));
+        assert!(!standalone.evaluate(&env, &flags));
+
+        env.set_file("other mod.esp", DependencyState::Active);
+        assert!(patch.evaluate(&env, &flags));
+
+        env.remove_file("other mod.esp");
+        env.set_game_version(Some("1.10"));
+        assert!(patch.evaluate(&env, &flags));
+        assert!(standalone.evaluate(&env, &flags));
+
+        env.set_game_version(None);
+        assert!(!patch.evaluate(&env, &flags));
+    }
+}
@@ -0,0 +1,496 @@
+use std::{fmt, io, path::PathBuf};
+
+use quick_xml::{events::Event, DeError, Reader};
+use serde_path_to_error::Segment;
+
+use crate::path::PathError;
+
+/// A line and column in a document, both starting at 1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+impl Position {
+    fn at(xml: &str, offset: usize) -> Self {
+        let before = &xml[..floor_char_boundary(xml, offset)];
+        let line_start = before.rfind('\n').map(|idx| idx + 1).unwrap_or(0);
+        Self {
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+}
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    /// Reading or writing failed.
+    Io(io::Error),
+    /// The document is not valid in its encoding.
+    Encoding(String),
+    /// The document is not well-formed xml.
+    Xml {
+        message: String,
+        path: String,
+        position: Option<Position>,
+    },
+    /// The document does not match the schema, e.g. a missing element or an
+    /// unknown attribute value.
+    Schema {
+        message: String,
+        path: String,
+        position: Option<Position>,
+    },
+    /// The document matches the schema, but can not be installed, e.g. a
+    /// single choice group with several required plugins.
+    Semantic {
+        message: String,
+        path: String,
+        position: Option<Position>,
+    },
+    /// A path in the document is absolute or leaves its root.
+    Path(PathError),
+    /// The choices of another mod manager can not be read.
+    Import {
+        format: &'static str,
+        message: String,
+    },
+    /// An error in the named file.
+    File { file: PathBuf, source: Box<Error> },
+}
+impl Error {
+    /// Adds the file the error occurred in.
+    pub fn with_file(self, file: impl Into<PathBuf>) -> Self {
+        Self::File {
+            file: file.into(),
+            source: Box::new(self),
+        }
+    }
+
+    /// The element path, e.g. `installSteps/installStep[2]/optionalFileGroups/group[1]`.
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            Self::Xml { path, .. } | Self::Schema { path, .. } | Self::Semantic { path, .. } => {
+                Some(path)
+            }
+            Self::File { source, .. } => source.path(),
+            _ => None,
+        }
+    }
+
+    pub fn position(&self) -> Option<Position> {
+        match self {
+            Self::Xml { position, .. }
+            | Self::Schema { position, .. }
+            | Self::Semantic { position, .. } => *position,
+            Self::File { source, .. } => source.position(),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn deserialize(xml: &str, error: serde_path_to_error::Error<DeError>) -> Self {
+        let steps = steps(error.path());
+        let error = error.into_inner();
+        if let DeError::InvalidXml(e) = error {
+            return Self::malformed(xml, e.to_string());
+        }
+
+        let (path, position) = locate(xml, &steps);
+        Self::Schema {
+            message: error.to_string(),
+            path,
+            position,
+        }
+    }
+
+    pub(crate) fn semantic(xml: &str, steps: &[Step], message: String) -> Self {
+        let (path, position) = locate(xml, steps);
+        Self::Semantic {
+            message,
+            path,
+            position,
+        }
+    }
+
+    /// Reads the document up to the first syntax error.
+    fn malformed(xml: &str, message: String) -> Self {
+        let mut reader = Reader::from_str(xml);
+        let mut open = Vec::<(String, usize)>::new();
+        let mut siblings = vec![Vec::<String>::new()];
+
+        let position = loop {
+            match reader.read_event() {
+                Ok(Event::Start(e)) => {
+                    let name = String::from_utf8_lossy(e.name().as_ref()).into_owned();
+                    let level = siblings.last_mut().unwrap();
+                    level.push(name.clone());
+                    let index = level.iter().filter(|n| **n == name).count();
+                    open.push((name, index));
+                    siblings.push(Vec::new());
+                }
+                Ok(Event::End(_)) => {
+                    open.pop();
+                    siblings.pop();
+                }
+                Ok(Event::Eof) => break None,
+                Ok(_) => {}
+                Err(_) => break Some(Position::at(xml, reader.buffer_position())),
+            }
+        };
+
+        // Elements which may repeat are indexed, as in `locate`.
+        let steps = open
+            .iter()
+            .skip(1)
+            .zip(&open)
+            .map(|((name, index), (parent, _))| {
+                let repeats = REPEATED.contains(&name.as_str()) || LISTS.contains(&parent.as_str());
+                Step::Child(name.clone(), repeats.then_some(index - 1))
+            })
+            .collect::<Vec<_>>();
+        let (path, _) = locate(xml, &steps);
+        Self::Xml {
+            message,
+            path,
+            position,
+        }
+    }
+}
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{e}"),
+            Self::Encoding(message) => write!(f, "{message}"),
+            Self::Path(e) => write!(f, "{e}"),
+            Self::Import { format, message } => write!(f, "invalid {format}: {message}"),
+            Self::Xml {
+                message,
+                path,
+                position,
+            }
+            | Self::Schema {
+                message,
+                path,
+                position,
+            }
+            | Self::Semantic {
+                message,
+                path,
+                position,
+            } => {
+                if !path.is_empty() {
+                    write!(f, "{path}: ")?;
+                }
+                write!(f, "{message}")?;
+                if let Some(position) = position {
+                    write!(f, " at {position}")?;
+                }
+                Ok(())
+            }
+            Self::File { file, source } => write!(f, "{}: {source}", file.display()),
+        }
+    }
+}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::Path(e) => Some(e),
+            Self::File { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+impl From<PathError> for Error {
+    fn from(e: PathError) -> Self {
+        Self::Path(e)
+    }
+}
+impl From<quick_xml::Error> for Error {
+    fn from(e: quick_xml::Error) -> Self {
+        match e {
+            quick_xml::Error::Io(e) => Self::Io(io::Error::new(e.kind(), e.to_string())),
+            e => Self::Xml {
+                message: e.to_string(),
+                path: String::new(),
+                position: None,
+            },
+        }
+    }
+}
+impl From<DeError> for Error {
+    fn from(e: DeError) -> Self {
+        match e {
+            DeError::InvalidXml(e) => Self::from(e),
+            e => Self::Schema {
+                message: e.to_string(),
+                path: String::new(),
+                position: None,
+            },
+        }
+    }
+}
+
+/// The elements a config may have several of under the same parent.
+const REPEATED: &[&str] = &["installStep", "group", "plugin", "flag", "pattern"];
+/// The elements whose children may be any number of files or dependencies.
+const LISTS: &[&str] = &[
+    "requiredInstallFiles",
+    "files",
+    "moduleDependencies",
+    "visible",
+    "dependencies",
+];
+
+/// One step of an element path, relative to its parent element.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Step {
+    /// The child element with this name, or the n-th (from 0) of them.
+    Child(String, Option<usize>),
+    /// The n-th (from 0) child element, whatever its name.
+    Any(usize),
+    Attribute(String),
+}
+
+fn steps(path: &serde_path_to_error::Path) -> Vec<Step> {
+    let mut steps = Vec::new();
+    for segment in path.iter() {
+        match segment {
+            // Without a following index, the text or the single child.
+            Segment::Map { key } if key == "$value" || key == "$text" => steps.push(Step::Any(0)),
+            Segment::Map { key } => match key.strip_prefix('@') {
+                Some(attribute) => steps.push(Step::Attribute(attribute.to_string())),
+                None => steps.push(Step::Child(key.clone(), None)),
+            },
+            Segment::Seq { index } => match steps.last_mut() {
+                Some(Step::Child(_, nth)) => *nth = Some(*index),
+                Some(Step::Any(nth)) => *nth = *index,
+                _ => {}
+            },
+            Segment::Enum { .. } | Segment::Unknown => {}
+        }
+    }
+    steps
+}
+
+/// Follows the steps from the root element, giving the path as far as it
+/// exists and the position of the last element found.
+pub(crate) fn locate(xml: &str, steps: &[Step]) -> (String, Option<Position>) {
+    let mut reader = Reader::from_str(xml);
+    let mut path = Vec::<String>::new();
+    let mut position = None;
+
+    // The number of open elements, and of those on the path, counting the root.
+    let mut depth = 0;
+    let mut found = 0;
+    let mut siblings = Vec::<String>::new();
+
+    while found <= steps.len() {
+        let (start, empty) = match reader.read_event() {
+            Ok(Event::Start(e)) => (e, false),
+            Ok(Event::Empty(e)) => (e, true),
+            Ok(Event::End(_)) => {
+                depth -= 1;
+                if depth < found {
+                    break;
+                }
+                continue;
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            Ok(_) => continue,
+        };
+        let name = String::from_utf8_lossy(start.name().as_ref()).into_owned();
+        let offset = xml[..reader.buffer_position()].rfind('<').unwrap_or(0);
+
+        if depth == found {
+            siblings.push(name.clone());
+            let index = siblings.iter().filter(|n| **n == name).count() - 1;
+            let is_next = found == 0
+                || match &steps[found - 1] {
+                    Step::Child(n, nth) => *n == name && nth.unwrap_or(0) == index,
+                    Step::Any(nth) => *nth == siblings.len() - 1,
+                    Step::Attribute(_) => false,
+                };
+
+            if is_next {
+                if found > 0 {
+                    match steps[found - 1] {
+                        Step::Child(_, None) => path.push(name),
+                        _ => path.push(format!("{name}[{}]", index + 1)),
+                    }
+                }
+                position = Some(Position::at(xml, offset));
+                found += 1;
+                siblings.clear();
+
+                if let Some(Step::Attribute(attribute)) = steps.get(found - 1) {
+                    path.push(format!("@{attribute}"));
+                    break;
+                }
+                if found > steps.len() || empty {
+                    break;
+                }
+            }
+        }
+        if !empty {
+            depth += 1;
+        }
+    }
+
+    (path.join("/"), position)
+}
+
+/// `str::floor_char_boundary`, which is not stable yet.
+fn floor_char_boundary(s: &str, mut idx: usize) -> usize {
+    idx = idx.min(s.len());
+    while !s.is_char_boundary(idx) {
+        idx -= 1;
+    }
+    idx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Error, Position};
+    use crate::{Config, Info};
+
+    const XML: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<config>
+    <moduleName>Example Mod</moduleName>
+    <requiredInstallFiles>
+        <file source="core.esp"/>
+        <folder source="core" priority="high"/>
+    </requiredInstallFiles>
+    <installSteps order="Explicit">
+        <installStep name="First">
+            <optionalFileGroups order="Explicit">
+                <group name="Options" type="SelectAny">
+                    <plugins order="Explicit">
+                        <plugin name="A"><description/></plugin>
+                    </plugins>
+                </group>
+            </optionalFileGroups>
+        </installStep>
+        <installStep name="Second">
+            <optionalFileGroups order="Explicit">
+                <group name="Options" type="SelectAny">
+                    <plugins order="Explicit">
+                        <plugin name="A"><description/></plugin>
+                        <plugin name="B"/>
+                    </plugins>
+                </group>
+            </optionalFileGroups>
+        </installStep>
+    </installSteps>
+</config>
+"#;
+
+    #[test]
+    pub fn schema_errors() {
+        let xml = XML.replace(r#" priority="high""#, "");
+        let err = Config::try_from(xml.as_str()).unwrap_err();
+        assert!(matches!(err, Error::Schema { .. }));
+        assert_eq!(
+            err.path(),
+            Some("installSteps/installStep[2]/optionalFileGroups/group[1]/plugins/plugin[2]")
+        );
+        assert_eq!(
+            err.position(),
+            Some(Position {
+                line: 23,
+                column: 25
+            })
+        );
+
+        let err = Config::try_from(XML).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "requiredInstallFiles/folder[1]/@priority: invalid digit found in string at line 6, column 9"
+        );
+    }
+
+    #[test]
+    pub fn semantic_errors() {
+        let required =
+            r#"<description/><typeDescriptor><type name="Required"/></typeDescriptor></plugin>"#;
+        let mut xml = XML.replace(r#" priority="high""#, "").replace(
+            r#"<plugin name="B"/>"#,
+            &format!(r#"<plugin name="B">{required}"#),
+        );
+        let at = xml.rfind("<description/></plugin>").unwrap();
+        xml.replace_range(at..at + "<description/></plugin>".len(), required);
+        assert!(Config::try_from(xml.as_str()).is_ok());
+
+        let at = xml.rfind("SelectAny").unwrap();
+        xml.replace_range(at..at + "SelectAny".len(), "SelectExactlyOne");
+        let err = Config::try_from(xml.as_str()).unwrap_err();
+        assert!(matches!(err, Error::Semantic { .. }), "{err:?}");
+        assert_eq!(
+            err.path(),
+            Some("installSteps/installStep[2]/optionalFileGroups/group[1]/plugins/plugin[2]")
+        );
+        assert_eq!(err.position().unwrap().line, 23);
+    }
+
+    #[test]
+    pub fn lenient_values() {
+        // Mod managers treat anything but true as false.
+        let xml = XML
+            .replace(r#"priority="high""#, r#"alwaysInstall="yes""#)
+            .replace("<plugin name=\"B\"/>", "");
+        assert!(Config::try_from(xml.as_str()).is_ok());
+    }
+
+    #[test]
+    pub fn malformed_documents() {
+        let xml = XML
+            .replace(r#" priority="high""#, "")
+            .replace("</plugins>", "");
+        let err = Config::try_from(xml.as_str()).unwrap_err();
+        assert!(matches!(err, Error::Xml { .. }), "{err:?}");
+        assert_eq!(
+            err.path(),
+            Some("installSteps/installStep[1]/optionalFileGroups/group[1]/plugins")
+        );
+        assert_eq!(err.position().unwrap().line, 15);
+
+        let xml = XML
+            .replace(r#" priority="high""#, "")
+            .replace("<plugin name=\"B\"/>", "");
+        let at = xml.rfind("</plugins>").unwrap();
+        let xml = format!("{}{}", &xml[..at], &xml[at + "</plugins>".len()..]);
+        let err = Config::try_from(xml.as_str()).unwrap_err();
+        assert!(matches!(err, Error::Xml { .. }), "{err:?}");
+        assert_eq!(
+            err.path(),
+            Some("installSteps/installStep[2]/optionalFileGroups/group[1]/plugins")
+        );
+    }
+
+    #[test]
+    pub fn file_context() {
+        let err = Info::try_from(&b"<fomod>\n<Name>Example</Version>\n</fomod>"[..]).unwrap_err();
+        assert!(matches!(err, Error::Xml { .. }), "{err:?}");
+
+        let err = err.with_file("fomod/info.xml");
+        assert!(err.to_string().starts_with("fomod/info.xml: "));
+        assert!(err.position().is_some());
+
+        let mut bytes = vec![0xFF, 0xFE];
+        bytes.extend([0x3C, 0x00, 0x00, 0xD8]);
+        assert!(matches!(
+            Info::try_from(bytes.as_slice()),
+            Err(Error::Encoding(_))
+        ));
+    }
+}
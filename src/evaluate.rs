@@ -0,0 +1,320 @@
+use std::{cmp::Ordering, collections::HashMap};
+
+use crate::{
+    Dependency, DependencyOperator, DependencyPluginType, FileDependency, FlagDependency,
+    GameEnvironment, Plugin, PluginTypeDescriptorEnum, PluginTypeEnum, VersionDependency,
+};
+
+/// Condition flags as set by the plugins selected so far.
+///
+/// A flag which has never been set has the empty string as value, so a
+/// `flagDependency` with an empty `value` matches an unset flag.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Flags(HashMap<String, String>);
+impl Flags {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn get(&self, flag: &str) -> &str {
+        self.0.get(flag).map(String::as_str).unwrap_or_default()
+    }
+    pub fn set(&mut self, flag: &str, value: &str) {
+        self.0.insert(flag.to_string(), value.to_string());
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+impl<'a> Extend<&'a FlagDependency> for Flags {
+    fn extend<I: IntoIterator<Item = &'a FlagDependency>>(&mut self, iter: I) {
+        for fd in iter {
+            self.set(&fd.flag, &fd.value);
+        }
+    }
+}
+impl<'a> FromIterator<&'a FlagDependency> for Flags {
+    fn from_iter<I: IntoIterator<Item = &'a FlagDependency>>(iter: I) -> Self {
+        let mut flags = Self::new();
+        flags.extend(iter);
+        flags
+    }
+}
+impl From<HashMap<String, String>> for Flags {
+    fn from(map: HashMap<String, String>) -> Self {
+        Self(map)
+    }
+}
+
+impl Dependency {
+    pub fn evaluate<E: GameEnvironment + ?Sized>(&self, env: &E, flags: &Flags) -> bool {
+        match self {
+            Self::File(fd) => fd.evaluate(env),
+            Self::Flag(fd) => fd.evaluate(flags),
+            Self::Game(vd) => env
+                .game_version()
+                .map(|v| vd.is_satisfied_by(v))
+                .unwrap_or(false),
+            Self::Fomm(vd) => env
+                .fomm_version()
+                .map(|v| vd.is_satisfied_by(v))
+                .unwrap_or(false),
+            Self::Dependency(op) => op.evaluate(env, flags),
+        }
+    }
+}
+
+impl DependencyOperator<Dependency> {
+    /// An empty `And` is true, an empty `Or` is false.
+    pub fn evaluate<E: GameEnvironment + ?Sized>(&self, env: &E, flags: &Flags) -> bool {
+        match self {
+            Self::And(list) => list.iter().all(|d| d.evaluate(env, flags)),
+            Self::Or(list) => list.iter().any(|d| d.evaluate(env, flags)),
+        }
+    }
+}
+
+impl PluginTypeDescriptorEnum {
+    /// The effective plugin type given the current environment and flags.
+    pub fn resolve<E: GameEnvironment + ?Sized>(&self, env: &E, flags: &Flags) -> PluginTypeEnum {
+        match self {
+            Self::PluginType(t) => *t,
+            Self::DependencyType(dpt) => dpt.resolve(env, flags),
+        }
+    }
+}
+
+impl DependencyPluginType {
+    /// The type of the first pattern whose dependencies hold, or the default type.
+    pub fn resolve<E: GameEnvironment + ?Sized>(&self, env: &E, flags: &Flags) -> PluginTypeEnum {
+        self.patterns
+            .iter()
+            .find(|p| p.dependencies.evaluate(env, flags))
+            .map(|p| p.typ)
+            .unwrap_or(self.default_type)
+    }
+}
+
+impl Plugin {
+    /// The type of this plugin, `Optional` when it has no type descriptor.
+    pub fn plugin_type<E: GameEnvironment + ?Sized>(
+        &self,
+        env: &E,
+        flags: &Flags,
+    ) -> PluginTypeEnum {
+        self.type_descriptor
+            .as_ref()
+            .map(|td| td.resolve(env, flags))
+            .unwrap_or(PluginTypeEnum::Optional)
+    }
+
+    /// Whether this plugin is `NotUsable` whatever the flags and the environment.
+    pub fn is_never_usable(&self) -> bool {
+        match &self.type_descriptor {
+            Some(PluginTypeDescriptorEnum::PluginType(t)) => *t == PluginTypeEnum::NotUsable,
+            Some(PluginTypeDescriptorEnum::DependencyType(dpt)) => {
+                dpt.default_type == PluginTypeEnum::NotUsable
+                    && dpt
+                        .patterns
+                        .iter()
+                        .all(|p| p.typ == PluginTypeEnum::NotUsable)
+            }
+            None => false,
+        }
+    }
+}
+
+impl FileDependency {
+    pub fn evaluate<E: GameEnvironment + ?Sized>(&self, env: &E) -> bool {
+        env.file_state(&self.file_name) == self.state
+    }
+}
+
+impl FlagDependency {
+    pub fn evaluate(&self, flags: &Flags) -> bool {
+        flags.get(&self.flag) == self.value
+    }
+}
+
+impl VersionDependency {
+    /// True when `version` is equal to or newer than the required version.
+    pub fn is_satisfied_by(&self, version: &str) -> bool {
+        compare_versions(version, &self.version) != Ordering::Less
+    }
+}
+
+/// Compares dotted version strings component wise, numerically where possible.
+///
+/// Missing components count as zero, so `1.2` equals `1.2.0`.
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let split = |s: &str| -> Vec<String> {
+        s.trim()
+            .split(['.', '-', '_', ','])
+            .map(|c| c.trim().to_string())
+            .collect()
+    };
+    let a = split(a);
+    let b = split(b);
+
+    for i in 0..a.len().max(b.len()) {
+        let ca = a.get(i).map(String::as_str).unwrap_or("0");
+        let cb = b.get(i).map(String::as_str).unwrap_or("0");
+
+        let ord = match (ca.parse::<u64>(), cb.parse::<u64>()) {
+            (Ok(na), Ok(nb)) => na.cmp(&nb),
+            _ => ca.cmp(cb),
+        };
+        if ord != Ordering::Equal {
+            return ord;
+        }
+    }
+    Ordering::Equal
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+
+    use super::{compare_versions, Flags};
+    use crate::{spec::types::DependencyState, Config, MemoryEnvironment, PluginTypeEnum};
+
+    const XML: &str = r#"
+        <config xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance"
+            xsi:noNamespaceSchemaLocation="http://qconsulting.ca/fo3/ModConfig5.0.xsd">
+
+            <moduleName>Example Mod</moduleName>
+
+            <moduleDependencies operator="And">
+                <fileDependency file="depend1.plugin" state="Active"/>
+                <dependencies operator="Or">
+                    <fileDependency file="depend2v1.plugin" state="Active"/>
+                    <fileDependency file="depend2v2.plugin" state="Active"/>
+                    <flagDependency flag="force" value="on"/>
+                </dependencies>
+                <gameDependency version="1.7.23"/>
+                <fommDependency version="0.13"/>
+            </moduleDependencies>
+        </config>
+        "#;
+
+    fn env() -> MemoryEnvironment {
+        MemoryEnvironment::new()
+            .with_file("depend1.plugin", DependencyState::Active)
+            .with_game_version("1.7.23.0")
+            .with_fomm_version("0.14.11")
+    }
+
+    #[test]
+    pub fn module_dependencies() {
+        let config = Config::try_from(XML).unwrap();
+        let deps = config.module_dependencies.unwrap();
+
+        assert!(!deps.evaluate(&env(), &Flags::new()));
+
+        let env = env().with_file("Depend2v2.plugin", DependencyState::Active);
+        assert!(deps.evaluate(&env, &Flags::new()));
+
+        let env = env.with_game_version("1.7.22");
+        assert!(!deps.evaluate(&env, &Flags::new()));
+    }
+
+    #[test]
+    pub fn flags() {
+        let config = Config::try_from(XML).unwrap();
+        let deps = config.module_dependencies.unwrap();
+
+        let mut flags = Flags::new();
+        flags.set("force", "on");
+        assert!(deps.evaluate(&env(), &flags));
+
+        flags.set("force", "off");
+        assert!(!deps.evaluate(&env(), &flags));
+    }
+
+    #[test]
+    pub fn unknown_environment() {
+        let config = Config::try_from(XML).unwrap();
+        let deps = config.module_dependencies.unwrap();
+
+        let mut flags = Flags::new();
+        flags.set("force", "on");
+        let env = MemoryEnvironment::new().with_file("depend1.plugin", DependencyState::Active);
+        assert!(!deps.evaluate(&env, &flags));
+    }
+
+    #[test]
+    pub fn dependency_type() {
+        let xml = r#"
+        <config xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance"
+            xsi:noNamespaceSchemaLocation="http://qconsulting.ca/fo3/ModConfig5.0.xsd">
+
+            <moduleName>Example Mod</moduleName>
+
+            <installSteps order="Explicit">
+                <installStep name="Patches">
+                    <optionalFileGroups order="Explicit">
+                        <group name="Patches" type="SelectAny">
+                            <plugins order="Explicit">
+                                <plugin name="Patch">
+                                    <description>Patch for Other Mod</description>
+                                    <typeDescriptor>
+                                        <dependencyType>
+                                            <defaultType name="NotUsable"/>
+                                            <patterns>
+                                                <pattern>
+                                                    <dependencies operator="And">
+                                                        <fileDependency file="other.esp" state="Active"/>
+                                                    </dependencies>
+                                                    <type name="Recommended"/>
+                                                </pattern>
+                                                <pattern>
+                                                    <dependencies>
+                                                        <fileDependency file="other.esp" state="Inactive"/>
+                                                        <flagDependency flag="patches" value="on"/>
+                                                    </dependencies>
+                                                    <type name="Optional"/>
+                                                </pattern>
+                                            </patterns>
+                                        </dependencyType>
+                                    </typeDescriptor>
+                                </plugin>
+                            </plugins>
+                        </group>
+                    </optionalFileGroups>
+                </installStep>
+            </installSteps>
+        </config>
+        "#;
+
+        let config = Config::try_from(xml).unwrap();
+        let group = &config.install_steps.list()[0].optional_file_groups.list()[0];
+        let plugin = &group.plugins.inner().list()[0];
+
+        let mut flags = Flags::new();
+        let env = MemoryEnvironment::new();
+        assert_eq!(plugin.plugin_type(&env, &flags), PluginTypeEnum::NotUsable);
+
+        let env = env.with_file("other.esp", DependencyState::Inactive);
+        assert_eq!(plugin.plugin_type(&env, &flags), PluginTypeEnum::NotUsable);
+        flags.set("patches", "on");
+        assert_eq!(plugin.plugin_type(&env, &flags), PluginTypeEnum::Optional);
+
+        let env = env.with_file("other.esp", DependencyState::Active);
+        assert_eq!(
+            plugin.plugin_type(&env, &flags),
+            PluginTypeEnum::Recommended
+        );
+    }
+
+    #[test]
+    pub fn versions() {
+        assert_eq!(compare_versions("1.2", "1.2.0"), Ordering::Equal);
+        assert_eq!(compare_versions("1.10", "1.9"), Ordering::Greater);
+        assert_eq!(compare_versions("0.13.21", "0.14"), Ordering::Less);
+    }
+}
@@ -0,0 +1,344 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+    Config, Dependency, DependencyOperator, FlagDependency, Group, InstallStep, Plugin,
+    PluginTypeDescriptorEnum,
+};
+
+/// The values each flag can possibly have; the empty string stands for an
+/// unset flag, as in `Flags`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FlagValues(BTreeMap<String, BTreeSet<String>>);
+impl FlagValues {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The possible values of `flag`; only `""` for a flag nothing sets.
+    pub fn values(&self, flag: &str) -> Vec<&str> {
+        match self.0.get(flag) {
+            Some(values) => values.iter().map(String::as_str).collect(),
+            None => vec![""],
+        }
+    }
+    pub fn can_be(&self, flag: &str, value: &str) -> bool {
+        match self.0.get(flag) {
+            Some(values) => values.contains(value),
+            None => value.is_empty(),
+        }
+    }
+    /// The flags which can have a value besides unset.
+    pub fn flags(&self) -> impl Iterator<Item = &str> {
+        self.0.keys().map(String::as_str)
+    }
+
+    fn add(&mut self, flag: &str, value: &str) {
+        self.0
+            .entry(flag.to_string())
+            .or_insert_with(|| BTreeSet::from([String::new()]))
+            .insert(value.to_string());
+    }
+
+    /// Whether `dependency` can hold with these values. The environment is
+    /// unknown, so file and version dependencies can always hold.
+    pub fn may_hold(&self, dependency: &Dependency) -> bool {
+        match dependency {
+            Dependency::Flag(fd) => self.can_be(&fd.flag, &fd.value),
+            Dependency::File(_) | Dependency::Game(_) | Dependency::Fomm(_) => true,
+            Dependency::Dependency(DependencyOperator::Or(list)) => {
+                list.iter().any(|d| self.may_hold(d))
+            }
+            Dependency::Dependency(DependencyOperator::And(list)) => {
+                let mut required = BTreeMap::new();
+                list.iter().all(|d| match d {
+                    Dependency::Flag(fd) => {
+                        *required.entry(&fd.flag).or_insert(&fd.value) == &fd.value
+                            && self.can_be(&fd.flag, &fd.value)
+                    }
+                    d => self.may_hold(d),
+                })
+            }
+        }
+    }
+}
+
+/// Where a step reads a flag.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FlagRead<'a> {
+    /// In the `visible` condition of the step.
+    Visible,
+    /// In the dependency type of a plugin of the step.
+    PluginType {
+        group: &'a Group,
+        plugin: &'a Plugin,
+    },
+}
+
+/// A flag value read by a step, which only a later step can set.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LateFlag<'a> {
+    pub step: &'a InstallStep,
+    pub read: FlagRead<'a>,
+    pub flag: String,
+    pub value: String,
+    /// The first step after which the flag can have the value.
+    pub set_by: &'a InstallStep,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StepFlow<'a> {
+    pub step: &'a InstallStep,
+    /// The flag values the step can see, as set by the steps before it.
+    pub before: FlagValues,
+    /// Whether the `visible` condition can hold.
+    pub reachable: bool,
+}
+
+/// The flags each step can see, found without making any choices.
+///
+/// Steps are visited in installation order; a step can be visited when its
+/// visibility can hold for some of the values before it, and any plugin
+/// which is not `NotUsable` can be selected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FlagFlow<'a> {
+    steps: Vec<StepFlow<'a>>,
+    after: FlagValues,
+    late: Vec<LateFlag<'a>>,
+}
+impl<'a> FlagFlow<'a> {
+    pub fn new(config: &'a Config) -> Self {
+        let mut steps = Vec::new();
+        let mut values = FlagValues::new();
+
+        for step in config.install_steps.sorted() {
+            let visible = step.visible.as_ref();
+            let reachable = visible.map(|v| values.may_hold(v)).unwrap_or(true);
+            steps.push(StepFlow {
+                step,
+                before: values.clone(),
+                reachable,
+            });
+            if !reachable {
+                continue;
+            }
+
+            // Values are only ever added, as the plugin setting a flag may
+            // not be selected, or the step may be skipped.
+            for plugin in plugins(step).map(|(_, plugin)| plugin) {
+                if !plugin.is_never_usable() {
+                    for fd in &plugin.condition_flags {
+                        values.add(&fd.flag, &fd.value);
+                    }
+                }
+            }
+        }
+
+        let mut late = Vec::new();
+        for (idx, flow) in steps.iter().enumerate().filter(|(_, f)| f.reachable) {
+            let mut reads = Vec::new();
+            if let Some(visible) = &flow.step.visible {
+                flag_reads(visible, &mut |fd| reads.push((FlagRead::Visible, fd)));
+            }
+            for (group, plugin) in plugins(flow.step) {
+                if let Some(PluginTypeDescriptorEnum::DependencyType(dpt)) = &plugin.type_descriptor
+                {
+                    for pattern in &dpt.patterns {
+                        flag_reads(&pattern.dependencies, &mut |fd| {
+                            reads.push((FlagRead::PluginType { group, plugin }, fd))
+                        });
+                    }
+                }
+            }
+
+            for (read, fd) in reads {
+                if flow.before.can_be(&fd.flag, &fd.value) {
+                    continue;
+                }
+                // The values after a step are the values before the next one.
+                let later = steps[idx + 1..]
+                    .iter()
+                    .map(|f| &f.before)
+                    .chain([&values])
+                    .position(|v| v.can_be(&fd.flag, &fd.value));
+                if let Some(later) = later {
+                    late.push(LateFlag {
+                        step: flow.step,
+                        read,
+                        flag: fd.flag.clone(),
+                        value: fd.value.clone(),
+                        set_by: steps[idx + later].step,
+                    });
+                }
+            }
+        }
+
+        Self {
+            steps,
+            after: values,
+            late,
+        }
+    }
+
+    /// Every step in installation order.
+    pub fn steps(&self) -> &[StepFlow<'a>] {
+        &self.steps
+    }
+    /// The flag values after the last step, as seen by conditional file installs.
+    pub fn after(&self) -> &FlagValues {
+        &self.after
+    }
+    /// The steps whose `visible` condition can never hold.
+    pub fn unreachable_steps(&self) -> Vec<&'a InstallStep> {
+        self.steps
+            .iter()
+            .filter(|f| !f.reachable)
+            .map(|f| f.step)
+            .collect()
+    }
+    /// The flags read by reachable steps which only later steps set.
+    pub fn late_flags(&self) -> &[LateFlag<'a>] {
+        &self.late
+    }
+}
+
+fn plugins(step: &InstallStep) -> impl Iterator<Item = (&Group, &Plugin)> {
+    step.optional_file_groups.list().iter().flat_map(|group| {
+        group
+            .plugins
+            .inner()
+            .list()
+            .iter()
+            .map(move |plugin| (group, plugin))
+    })
+}
+
+fn flag_reads<'a>(dependency: &'a Dependency, f: &mut impl FnMut(&'a FlagDependency)) {
+    match dependency {
+        Dependency::Flag(fd) => f(fd),
+        Dependency::Dependency(DependencyOperator::And(list) | DependencyOperator::Or(list)) => {
+            for dependency in list {
+                flag_reads(dependency, f);
+            }
+        }
+        Dependency::File(_) | Dependency::Game(_) | Dependency::Fomm(_) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FlagFlow, FlagRead};
+    use crate::{lint, Config, LintCode};
+
+    const XML: &str = r#"
+        <config>
+            <moduleName>Example Mod</moduleName>
+            <installSteps order="Explicit">
+                <installStep name="A">
+                    <optionalFileGroups order="Explicit">
+                        <group name="Options" type="SelectExactlyOne">
+                            <plugins order="Explicit">
+                                <plugin name="On">
+                                    <description/>
+                                    <conditionFlags><flag name="a">On</flag></conditionFlags>
+                                </plugin>
+                                <plugin name="Patch">
+                                    <description/>
+                                    <typeDescriptor>
+                                        <dependencyType>
+                                            <defaultType name="Optional"/>
+                                            <patterns>
+                                                <pattern>
+                                                    <dependencies><flagDependency flag="b" value="On"/></dependencies>
+                                                    <type name="Recommended"/>
+                                                </pattern>
+                                            </patterns>
+                                        </dependencyType>
+                                    </typeDescriptor>
+                                </plugin>
+                            </plugins>
+                        </group>
+                    </optionalFileGroups>
+                </installStep>
+                <installStep name="B">
+                    <visible><flagDependency flag="a" value="On"/></visible>
+                    <optionalFileGroups order="Explicit">
+                        <group name="Options" type="SelectAny">
+                            <plugins order="Explicit">
+                                <plugin name="Off">
+                                    <description/>
+                                    <conditionFlags><flag name="a">Off</flag></conditionFlags>
+                                </plugin>
+                            </plugins>
+                        </group>
+                    </optionalFileGroups>
+                </installStep>
+                <installStep name="C">
+                    <visible>
+                        <dependencies operator="And">
+                            <flagDependency flag="a" value="Off"/>
+                            <flagDependency flag="b" value="On"/>
+                        </dependencies>
+                    </visible>
+                    <optionalFileGroups order="Explicit">
+                        <group name="Options" type="SelectAny">
+                            <plugins order="Explicit">
+                                <plugin name="C"><description/></plugin>
+                            </plugins>
+                        </group>
+                    </optionalFileGroups>
+                </installStep>
+                <installStep name="D">
+                    <optionalFileGroups order="Explicit">
+                        <group name="Options" type="SelectAny">
+                            <plugins order="Explicit">
+                                <plugin name="B">
+                                    <description/>
+                                    <conditionFlags><flag name="b">On</flag></conditionFlags>
+                                </plugin>
+                            </plugins>
+                        </group>
+                    </optionalFileGroups>
+                </installStep>
+            </installSteps>
+        </config>
+        "#;
+
+    #[test]
+    pub fn flag_flow() {
+        let config = Config::try_from(XML).unwrap();
+        let flow = FlagFlow::new(&config);
+
+        let steps = flow.steps();
+        assert_eq!(steps[1].before.values("a"), vec!["", "On"]);
+        assert_eq!(steps[2].before.values("a"), vec!["", "Off", "On"]);
+        assert_eq!(steps[2].before.values("b"), vec![""]);
+        assert_eq!(flow.after().values("b"), vec!["", "On"]);
+
+        let unreachable = flow.unreachable_steps();
+        assert_eq!(unreachable.len(), 1);
+        assert_eq!(unreachable[0].name, "C");
+
+        // C is unreachable, so only the plugin type of A reads b too early.
+        let late = flow.late_flags();
+        assert_eq!(late.len(), 1);
+        assert_eq!(late[0].step.name, "A");
+        assert_eq!(late[0].set_by.name, "D");
+        assert_eq!(late[0].flag, "b");
+        assert!(
+            matches!(late[0].read, FlagRead::PluginType { plugin, .. } if plugin.name == "Patch")
+        );
+
+        let lints = lint(&config)
+            .into_iter()
+            .filter(|l| matches!(l.code, LintCode::UnreachableStep | LintCode::LateFlag))
+            .map(|l| l.path)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lints,
+            vec![
+                "installSteps/installStep[1]/optionalFileGroups/group[1]/plugins/plugin[2]/typeDescriptor",
+                "installSteps/installStep[3]/visible"
+            ]
+        );
+    }
+}
@@ -0,0 +1,255 @@
+use std::{collections::HashMap, fs, io, path::Path};
+
+use crate::{
+    Error, FileOrigin, FomodPackage, InstallManifest, InstallPlan, ManifestEntry, PackageSource,
+};
+
+/// A file copy from the package into the installation.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct InstallOperation {
+    /// Path relative to the package root.
+    pub source: String,
+    /// Path relative to the installation root.
+    pub destination: String,
+    pub origin: FileOrigin,
+}
+
+impl<S: PackageSource> FomodPackage<S> {
+    /// The file copies which install a plan, without touching disk.
+    ///
+    /// Folders are expanded into their files. A file overwritten by a later
+    /// one with the same destination is left out, and destinations differing
+    /// only in case are merged into the casing which came first. Paths are
+    /// normalized with `paths`, failing on any path outside of its root.
+    pub fn operations(&self, plan: &InstallPlan) -> Result<Vec<InstallOperation>, Error> {
+        let mut operations = Vec::<InstallOperation>::new();
+        let mut casing = Casing::default();
+
+        for pf in &plan.files {
+            let with_file = |e| Error::from(e).with_file(pf.source());
+            let destination = pf.destination_with(self.paths()).map_err(with_file)?;
+            let source = self.paths().source(pf.source()).map_err(with_file)?;
+
+            let files = if pf.is_folder() {
+                if !self.contains_folder(&source) {
+                    return Err(not_found(pf.source()));
+                }
+                self.folder_entries(&source)
+                    .into_iter()
+                    .filter_map(|entry| {
+                        let source = self.resolve(&join(&source, entry))?;
+                        Some((source, join(&destination, entry)))
+                    })
+                    .collect()
+            } else {
+                let source = self
+                    .resolve(&source)
+                    .ok_or_else(|| not_found(pf.source()))?;
+                vec![(source, destination)]
+            };
+
+            for (source, destination) in files {
+                let destination = casing.apply(&destination);
+                operations.retain(|op| op.destination != destination);
+                operations.push(InstallOperation {
+                    source: source[self.root().len()..].to_string(),
+                    destination,
+                    origin: pf.origin.clone(),
+                });
+            }
+        }
+
+        Ok(operations)
+    }
+
+    /// Installs a plan into a directory, returning the written files.
+    pub fn install(
+        &mut self,
+        plan: &InstallPlan,
+        target: impl AsRef<Path>,
+    ) -> Result<InstallManifest, Error> {
+        let target = target.as_ref();
+        let mut manifest = InstallManifest {
+            module_name: self.config().module_name.clone(),
+            files: Vec::new(),
+        };
+
+        for op in self.operations(plan)? {
+            let bytes = self.read(&op.source)?;
+            manifest.files.push(ManifestEntry::new(&op, &bytes));
+            let path = target.join(&op.destination);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).map_err(|e| Error::from(e).with_file(parent))?;
+            }
+            fs::write(&path, bytes).map_err(|e| Error::from(e).with_file(&path))?;
+        }
+
+        Ok(manifest)
+    }
+}
+
+/// The first seen casing of every path.
+#[derive(Default)]
+struct Casing(HashMap<String, String>);
+impl Casing {
+    fn apply(&mut self, path: &str) -> String {
+        let mut cased = String::new();
+        for component in path.split('/').filter(|c| !c.is_empty()) {
+            if !cased.is_empty() {
+                cased.push('/');
+            }
+            cased.push_str(component);
+            cased = self
+                .0
+                .entry(cased.to_lowercase())
+                .or_insert_with(|| cased.clone())
+                .clone();
+        }
+        cased
+    }
+}
+
+fn join(folder: &str, path: &str) -> String {
+    match folder {
+        "" => path.to_string(),
+        folder => format!("{folder}/{path}"),
+    }
+}
+
+fn not_found(source: &str) -> Error {
+    Error::Io(io::Error::new(
+        io::ErrorKind::NotFound,
+        "source not in package",
+    ))
+    .with_file(source)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::{
+        Config, Error, FileOrigin, FomodPackage, InstallerSession, MemoryEnvironment, MemorySource,
+        PathError, PathNormalizer,
+    };
+
+    const CONFIG: &str = r#"
+        <config>
+            <moduleName>Example Mod</moduleName>
+            <requiredInstallFiles>
+                <folder source="Core" destination=""/>
+                <file source="readme.txt" destination="docs\readme.txt"/>
+            </requiredInstallFiles>
+            <installSteps order="Explicit">
+                <installStep name="Options">
+                    <optionalFileGroups order="Explicit">
+                        <group name="Textures" type="SelectExactlyOne">
+                            <plugins order="Explicit">
+                                <plugin name="HD">
+                                    <description/>
+                                    <files>
+                                        <folder source="hd" destination="textures" priority="1"/>
+                                        <file source="hd.esp" destination=""/>
+                                    </files>
+                                </plugin>
+                            </plugins>
+                        </group>
+                    </optionalFileGroups>
+                </installStep>
+            </installSteps>
+        </config>
+        "#;
+
+    fn package() -> FomodPackage<MemorySource> {
+        let source = MemorySource::new()
+            .with_file("fomod/ModuleConfig.xml", CONFIG)
+            .with_file("core/Textures/a.dds", "core a")
+            .with_file("core/Textures/b.dds", "core b")
+            .with_file("readme.txt", "readme")
+            .with_file("hd/A.dds", "hd a")
+            .with_file("hd/sub/c.dds", "hd c")
+            .with_file("plugins/hd.esp", "esp");
+        FomodPackage::new(source).unwrap()
+    }
+
+    #[test]
+    pub fn dry_run() {
+        let package = package();
+        let env = MemoryEnvironment::new();
+        let mut session = InstallerSession::new(package.config(), &env);
+        session.next().unwrap();
+
+        // hd.esp is not in the root of the package.
+        let plan = session.install_plan();
+        assert!(package.operations(&plan).is_err());
+
+        let config = Config::try_from(CONFIG.replace("hd.esp", "plugins/hd.esp").as_str()).unwrap();
+        let mut session = InstallerSession::new(&config, &env);
+        session.next().unwrap();
+        let operations = package.operations(&session.install_plan()).unwrap();
+
+        let files = operations
+            .iter()
+            .map(|op| (op.source.as_str(), op.destination.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            files,
+            vec![
+                ("core/Textures/b.dds", "Textures/b.dds"),
+                ("readme.txt", "docs/readme.txt"),
+                ("plugins/hd.esp", "hd.esp"),
+                ("hd/A.dds", "Textures/a.dds"),
+                ("hd/sub/c.dds", "Textures/sub/c.dds"),
+            ]
+        );
+        assert_eq!(operations[0].origin, FileOrigin::Required);
+    }
+
+    #[test]
+    pub fn install() {
+        let mut package = package();
+        let config = Config::try_from(CONFIG.replace("hd.esp", "plugins/hd.esp").as_str()).unwrap();
+        let env = MemoryEnvironment::new();
+        let mut session = InstallerSession::new(&config, &env);
+        session.next().unwrap();
+        let plan = session.install_plan();
+
+        let target = std::env::temp_dir().join(format!("fomod-install-{}", std::process::id()));
+        let manifest = package.install(&plan, &target);
+        let a = fs::read_to_string(target.join("Textures/a.dds"));
+        let esp = fs::read_to_string(target.join("hd.esp"));
+        fs::remove_dir_all(&target).unwrap();
+
+        assert_eq!(manifest.unwrap().files.len(), 5);
+        assert_eq!(a.unwrap(), "hd a");
+        assert_eq!(esp.unwrap(), "esp");
+    }
+
+    #[test]
+    pub fn unsafe_paths() {
+        let env = MemoryEnvironment::new();
+        let plan = |files: &str| {
+            let xml = format!(
+                "<config><moduleName>M</moduleName><requiredInstallFiles>{files}</requiredInstallFiles></config>"
+            );
+            let config = Config::try_from(xml.as_str()).unwrap();
+            InstallerSession::new(&config, &env).install_plan()
+        };
+        let package = package().with_paths(PathNormalizer::new().with_stripped_root("Data"));
+
+        let escaping = plan(r#"<file source="readme.txt" destination="..\..\readme.txt"/>"#);
+        assert!(matches!(
+            package.operations(&escaping),
+            Err(Error::File { source, .. }) if matches!(*source, Error::Path(PathError::Escaping(_)))
+        ));
+        assert!(escaping.validate_paths(package.paths()).is_err());
+
+        let absolute = plan(r#"<folder source="C:\Windows" destination="textures"/>"#);
+        assert!(package.operations(&absolute).is_err());
+
+        let data = plan(r#"<folder source=".\core\" destination="Data\"/>"#);
+        assert!(data.validate_paths(package.paths()).is_ok());
+        let operations = package.operations(&data).unwrap();
+        assert_eq!(operations[0].destination, "Textures/a.dds");
+    }
+}
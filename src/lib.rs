@@ -1,17 +1,50 @@
+pub mod choices;
+pub mod enumerate;
+pub mod environment;
+pub mod error;
+pub mod evaluate;
+pub mod flow;
+pub mod install;
+pub mod lint;
+pub mod manifest;
+pub mod package;
+pub mod path;
+pub mod plan;
+pub mod selection;
+pub mod session;
+pub mod solve;
 pub mod spec;
 
 use std::io::BufReader;
 
-use quick_xml::DeError;
-
-pub use crate::spec::{
-    types::{
-        FileDependency, FileTypeEnum, FlagDependency, HeaderImage, PluginTypeEnum,
-        SetConditionFlag, VersionDependency,
+pub use crate::{
+    choices::{Choices, ChoicesError, GroupChoices, PluginName, StepChoices},
+    enumerate::{Enumeration, Enumerator, Outcome},
+    environment::{GameEnvironment, MemoryEnvironment},
+    error::{Error, Position},
+    evaluate::Flags,
+    flow::{FlagFlow, FlagRead, FlagValues, LateFlag, StepFlow},
+    install::InstallOperation,
+    lint::{lint, Lint, LintCode},
+    manifest::{InstallManifest, ManifestEntry, ManifestMismatch},
+    package::{DirSource, FomodPackage, MemorySource, PackageSource},
+    path::{PathError, PathNormalizer},
+    plan::{FileOrigin, InstallPlan, PlannedFile, PluginChoice},
+    selection::SelectionError,
+    session::InstallerSession,
+    solve::{Solution, Solver, Target},
+    spec::{
+        types::{
+            DependencyState, FileDependency, FileTypeEnum, FlagDependency, HeaderImage,
+            PluginTypeEnum, SetConditionFlag, VersionDependency,
+        },
+        Info,
     },
-    Info,
 };
 
+#[cfg(feature = "zip")]
+pub use crate::package::ZipSource;
+
 use crate::spec::Config as SpecConfig;
 
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -58,18 +91,57 @@ impl From<SpecConfig> for Config {
         }
     }
 }
+impl From<Config> for SpecConfig {
+    fn from(config: Config) -> Self {
+        use spec::types::{ConditionalFileInstallList, ConditionalInstallPatternList, FileList};
+
+        let install_steps = Some(config.install_steps)
+            .filter(|is| !is.list().is_empty())
+            .map(spec::types::StepList::from);
+        let conditional_file_installs = Some(config.conditional_file_installs)
+            .filter(|cfi| !cfi.is_empty())
+            .map(|cfi| ConditionalFileInstallList {
+                patterns: ConditionalInstallPatternList {
+                    pattern: cfi
+                        .into_iter()
+                        .map(spec::types::ConditionalInstallPattern::from)
+                        .collect(),
+                },
+            });
+
+        Self {
+            module_name: config.module_name,
+            module_image: config.module_image,
+            module_dependencies: config
+                .module_dependencies
+                .map(spec::types::ModuleDependency::from),
+            required_install_files: Some(config.required_install_files)
+                .filter(|rif| !rif.is_empty())
+                .map(|rif| FileList { list: Some(rif) }),
+            install_steps,
+            conditional_file_installs,
+        }
+    }
+}
 impl TryFrom<&str> for Config {
-    type Error = DeError;
+    type Error = Error;
 
     fn try_from(string: &str) -> Result<Self, Self::Error> {
         Ok(Self::from(SpecConfig::try_from(string)?))
     }
 }
+impl TryFrom<&[u8]> for Config {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        Ok(Self::from(SpecConfig::try_from(bytes)?))
+    }
+}
 impl<T> TryFrom<BufReader<T>> for Config
 where
     T: std::io::Read,
 {
-    type Error = DeError;
+    type Error = Error;
 
     fn try_from(reader: BufReader<T>) -> Result<Self, Self::Error> {
         Ok(Self::from(SpecConfig::try_from(reader)?))
@@ -97,6 +169,26 @@ impl From<crate::spec::types::CompositeDependency> for Dependency {
         }
     }
 }
+impl From<Dependency> for crate::spec::types::CompositeDependency {
+    fn from(dep: Dependency) -> Self {
+        match dep {
+            Dependency::File(f) => Self::File(f),
+            Dependency::Flag(f) => Self::Flag(f),
+            Dependency::Game(v) => Self::Game(v),
+            Dependency::Fomm(v) => Self::Fomm(v),
+            Dependency::Dependency(op) => Self::Dependency(spec::types::ModuleDependency::from(op)),
+        }
+    }
+}
+impl From<Dependency> for crate::spec::types::ModuleDependency {
+    /// Anything but a nested operator is wrapped in an `And`.
+    fn from(dep: Dependency) -> Self {
+        match dep {
+            Dependency::Dependency(op) => Self::from(op),
+            dep => Self::from(DependencyOperator::And(vec![dep])),
+        }
+    }
+}
 
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum DependencyOperator<T> {
@@ -118,6 +210,21 @@ impl From<crate::spec::types::ModuleDependency> for DependencyOperator<Dependenc
         }
     }
 }
+impl From<DependencyOperator<Dependency>> for crate::spec::types::ModuleDependency {
+    fn from(op: DependencyOperator<Dependency>) -> Self {
+        use crate::spec::types::{CompositeDependency, DependencyOperator as DepOp};
+
+        let (operator, list) = match op {
+            DependencyOperator::And(list) => (DepOp::And, list),
+            DependencyOperator::Or(list) => (DepOp::Or, list),
+        };
+
+        Self {
+            operator,
+            list: list.into_iter().map(CompositeDependency::from).collect(),
+        }
+    }
+}
 
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum OrderEnum<T> {
@@ -125,47 +232,113 @@ pub enum OrderEnum<T> {
     Explicit(Vec<T>),
     Descending(Vec<T>),
 }
+impl<T> OrderEnum<T> {
+    pub fn list(&self) -> &Vec<T> {
+        match self {
+            Self::Ascending(v) | Self::Explicit(v) | Self::Descending(v) => v,
+        }
+    }
+}
+impl<T> OrderEnum<T>
+where
+    T: Ord,
+{
+    pub fn sorted(&self) -> Vec<&T> {
+        let mut v = self.list().iter().collect::<Vec<_>>();
+        match self {
+            Self::Ascending(_) => v.sort(),
+            Self::Explicit(_) => {}
+            Self::Descending(_) => v.sort_by(|a, b| b.cmp(a)),
+        }
+        v
+    }
+}
 impl<T> OrderEnum<T>
 where
     T: Ord,
     T: Clone,
 {
     pub fn vec_sorted(&self) -> Vec<T> {
+        self.sorted().into_iter().cloned().collect()
+    }
+    pub fn vec_sorted_mut(&mut self) -> &mut Vec<T> {
         match self {
             Self::Ascending(v) => {
-                let mut v = v.clone();
                 v.sort();
                 v
             }
-            Self::Explicit(v) => v.clone(),
+            Self::Explicit(v) => v,
             Self::Descending(v) => {
-                //FIXME Sort Descending
-                let mut v = v.clone();
-                v.sort();
+                v.sort_by(|a, b| b.cmp(a));
                 v
             }
         }
     }
-    pub fn vec_sorted_mut(&mut self) -> &mut Vec<T> {
-        match self {
-            Self::Ascending(v) => {
-                v.sort();
-                v
+}
+
+/// Compares names the way established installers order them.
+///
+/// Letters are compared case-insensitively and runs of digits by their
+/// numeric value, so "Option 10" sorts after "Option 9".
+pub fn natural_cmp(a: &str, b: &str) -> std::cmp::Ordering {
+    use std::cmp::Ordering;
+
+    let mut ai = a.chars().peekable();
+    let mut bi = b.chars().peekable();
+
+    loop {
+        match (ai.peek().copied(), bi.peek().copied()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(ca), Some(cb)) if ca.is_ascii_digit() && cb.is_ascii_digit() => {
+                let mut da = String::new();
+                while let Some(c) = ai.next_if(char::is_ascii_digit) {
+                    da.push(c);
+                }
+                let mut db = String::new();
+                while let Some(c) = bi.next_if(char::is_ascii_digit) {
+                    db.push(c);
+                }
+                let da = da.trim_start_matches('0');
+                let db = db.trim_start_matches('0');
+                let ord = da.len().cmp(&db.len()).then_with(|| da.cmp(db));
+                if ord != Ordering::Equal {
+                    return ord;
+                }
             }
-            Self::Explicit(v) => v,
-            Self::Descending(v) => {
-                //FIXME Sort Descending
-                v.sort();
-                v
+            (Some(ca), Some(cb)) => {
+                let ord = ca.to_lowercase().cmp(cb.to_lowercase());
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+                ai.next();
+                bi.next();
             }
         }
     }
 }
+
 impl<T> Default for OrderEnum<T> {
     fn default() -> Self {
         Self::Ascending(Vec::new())
     }
 }
+impl<T> OrderEnum<T> {
+    fn into_spec<U>(self) -> (spec::types::OrderEnum, Vec<U>)
+    where
+        U: From<T>,
+    {
+        use spec::types::OrderEnum;
+
+        let (order, list) = match self {
+            Self::Ascending(v) => (OrderEnum::Ascending, v),
+            Self::Explicit(v) => (OrderEnum::Explicit, v),
+            Self::Descending(v) => (OrderEnum::Descending, v),
+        };
+        (order, list.into_iter().map(U::from).collect())
+    }
+}
 impl From<spec::types::StepList> for OrderEnum<InstallStep> {
     fn from(step_list: spec::types::StepList) -> Self {
         let mut list = Vec::new();
@@ -184,6 +357,15 @@ impl From<spec::types::StepList> for OrderEnum<InstallStep> {
         }
     }
 }
+impl From<OrderEnum<InstallStep>> for spec::types::StepList {
+    fn from(steps: OrderEnum<InstallStep>) -> Self {
+        let (order, install_step) = steps.into_spec();
+        Self {
+            order,
+            install_step,
+        }
+    }
+}
 impl From<spec::types::GroupList> for OrderEnum<Group> {
     fn from(group_list: spec::types::GroupList) -> Self {
         let mut list = Vec::new();
@@ -197,6 +379,12 @@ impl From<spec::types::GroupList> for OrderEnum<Group> {
         }
     }
 }
+impl From<OrderEnum<Group>> for spec::types::GroupList {
+    fn from(groups: OrderEnum<Group>) -> Self {
+        let (order, group) = groups.into_spec();
+        Self { order, group }
+    }
+}
 impl From<spec::types::PluginList> for OrderEnum<Plugin> {
     fn from(plugin_list: spec::types::PluginList) -> Self {
         let mut list = Vec::new();
@@ -210,6 +398,12 @@ impl From<spec::types::PluginList> for OrderEnum<Plugin> {
         }
     }
 }
+impl From<OrderEnum<Plugin>> for spec::types::PluginList {
+    fn from(plugins: OrderEnum<Plugin>) -> Self {
+        let (order, plugin) = plugins.into_spec();
+        Self { order, plugin }
+    }
+}
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct InstallStep {
@@ -219,23 +413,36 @@ pub struct InstallStep {
 }
 impl PartialOrd for InstallStep {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.name.as_str().partial_cmp(other.name.as_str())
+        Some(self.cmp(other))
     }
 }
 impl Ord for InstallStep {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.name.as_str().cmp(other.name.as_str())
+        natural_cmp(&self.name, &other.name)
     }
 }
 impl From<spec::types::InstallStep> for InstallStep {
     fn from(install_step: spec::types::InstallStep) -> Self {
         Self {
             name: install_step.name,
-            visible: install_step.visible.map(|v| Dependency::from(v)),
+            visible: install_step
+                .visible
+                .map(|v| Dependency::Dependency(DependencyOperator::from(v))),
             optional_file_groups: OrderEnum::from(install_step.optional_file_groups),
         }
     }
 }
+impl From<InstallStep> for spec::types::InstallStep {
+    fn from(install_step: InstallStep) -> Self {
+        Self {
+            name: install_step.name,
+            visible: install_step
+                .visible
+                .map(spec::types::ModuleDependency::from),
+            optional_file_groups: spec::types::GroupList::from(install_step.optional_file_groups),
+        }
+    }
+}
 
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum GroupType<T> {
@@ -245,6 +452,17 @@ pub enum GroupType<T> {
     SelectAll(T),
     SelectAny(T),
 }
+impl<T> GroupType<T> {
+    pub fn inner(&self) -> &T {
+        match self {
+            Self::SelectAtLeastOne(t)
+            | Self::SelectAtMostOne(t)
+            | Self::SelectExactlyOne(t)
+            | Self::SelectAll(t)
+            | Self::SelectAny(t) => t,
+        }
+    }
+}
 impl From<(spec::types::GroupType, spec::types::PluginList)> for GroupType<OrderEnum<Plugin>> {
     fn from((gt, pl): (spec::types::GroupType, spec::types::PluginList)) -> Self {
         let oe = OrderEnum::from(pl);
@@ -259,6 +477,25 @@ impl From<(spec::types::GroupType, spec::types::PluginList)> for GroupType<Order
         }
     }
 }
+impl From<GroupType<OrderEnum<Plugin>>> for (spec::types::GroupType, spec::types::PluginList) {
+    fn from(gt: GroupType<OrderEnum<Plugin>>) -> Self {
+        use spec::types::{GroupType as SpecGroupType, PluginList};
+
+        match gt {
+            GroupType::SelectAtLeastOne(oe) => {
+                (SpecGroupType::SelectAtLeastOne, PluginList::from(oe))
+            }
+            GroupType::SelectAtMostOne(oe) => {
+                (SpecGroupType::SelectAtMostOne, PluginList::from(oe))
+            }
+            GroupType::SelectExactlyOne(oe) => {
+                (SpecGroupType::SelectExactlyOne, PluginList::from(oe))
+            }
+            GroupType::SelectAll(oe) => (SpecGroupType::SelectAll, PluginList::from(oe)),
+            GroupType::SelectAny(oe) => (SpecGroupType::SelectAny, PluginList::from(oe)),
+        }
+    }
+}
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Group {
@@ -273,14 +510,24 @@ impl From<spec::types::Group> for Group {
         }
     }
 }
+impl From<Group> for spec::types::Group {
+    fn from(group: Group) -> Self {
+        let (typ, plugins) = group.plugins.into();
+        Self {
+            name: group.name,
+            typ,
+            plugins,
+        }
+    }
+}
 impl PartialOrd for Group {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.name.as_str().partial_cmp(other.name.as_str())
+        Some(self.cmp(other))
     }
 }
 impl Ord for Group {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.name.as_str().cmp(other.name.as_str())
+        natural_cmp(&self.name, &other.name)
     }
 }
 
@@ -329,20 +576,42 @@ impl From<spec::types::Plugin> for Plugin {
         }
     }
 }
+impl From<Plugin> for spec::types::Plugin {
+    fn from(plugin: Plugin) -> Self {
+        use spec::types::{ConditionFlagList, FileList, Image, PluginTypeDescriptor};
+
+        Self {
+            name: plugin.name,
+            description: plugin.description,
+            image: plugin.image.map(|path| Image { path }),
+            files: Some(plugin.files)
+                .filter(|fl| !fl.is_empty())
+                .map(|fl| FileList { list: Some(fl) }),
+            condition_flags: Some(plugin.condition_flags)
+                .filter(|cfl| !cfl.is_empty())
+                .map(|cfl| ConditionFlagList {
+                    flag: cfl.into_iter().map(SetConditionFlag::from).collect(),
+                }),
+            type_descriptor: plugin.type_descriptor.map(|td| PluginTypeDescriptor {
+                value: spec::types::PluginTypeDescriptorEnum::from(td),
+            }),
+        }
+    }
+}
 impl PartialOrd for Plugin {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.name.as_str().partial_cmp(other.name.as_str())
+        Some(self.cmp(other))
     }
 }
 impl Ord for Plugin {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.name.as_str().cmp(other.name.as_str())
+        natural_cmp(&self.name, &other.name)
     }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum PluginTypeDescriptorEnum {
-    DependencyType(Vec<DependencyPattern>),
+    DependencyType(DependencyPluginType),
     PluginType(PluginTypeEnum),
 }
 impl From<spec::types::PluginTypeDescriptorEnum> for PluginTypeDescriptorEnum {
@@ -350,27 +619,68 @@ impl From<spec::types::PluginTypeDescriptorEnum> for PluginTypeDescriptorEnum {
         use spec::types::PluginTypeDescriptorEnum;
         match ptde {
             PluginTypeDescriptorEnum::DependencyType(dpt) => {
-                //FIXME: DependencyPluginType::default_type not accounted for!!
-                let mut list = Vec::new();
-                list.extend(
-                    dpt.patterns
-                        .pattern
-                        .iter()
-                        .map(|dp| DependencyPattern::from(dp.clone())),
-                );
-
-                Self::DependencyType(list)
+                Self::DependencyType(DependencyPluginType::from(dpt))
             }
             PluginTypeDescriptorEnum::PluginType(pt) => Self::PluginType(pt.name),
         }
     }
 }
+impl From<PluginTypeDescriptorEnum> for spec::types::PluginTypeDescriptorEnum {
+    fn from(ptde: PluginTypeDescriptorEnum) -> Self {
+        match ptde {
+            PluginTypeDescriptorEnum::DependencyType(dpt) => {
+                Self::DependencyType(spec::types::DependencyPluginType::from(dpt))
+            }
+            PluginTypeDescriptorEnum::PluginType(name) => {
+                Self::PluginType(spec::types::PluginType { name })
+            }
+        }
+    }
+}
 impl From<spec::types::PluginTypeDescriptor> for PluginTypeDescriptorEnum {
     fn from(ptd: spec::types::PluginTypeDescriptor) -> Self {
         Self::from(ptd.value)
     }
 }
 
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DependencyPluginType {
+    pub default_type: PluginTypeEnum,
+    pub patterns: Vec<DependencyPattern>,
+}
+impl From<spec::types::DependencyPluginType> for DependencyPluginType {
+    fn from(dpt: spec::types::DependencyPluginType) -> Self {
+        let mut patterns = Vec::new();
+        patterns.extend(
+            dpt.patterns
+                .pattern
+                .iter()
+                .map(|dp| DependencyPattern::from(dp.clone())),
+        );
+
+        Self {
+            default_type: dpt.default_type.name,
+            patterns,
+        }
+    }
+}
+impl From<DependencyPluginType> for spec::types::DependencyPluginType {
+    fn from(dpt: DependencyPluginType) -> Self {
+        Self {
+            default_type: spec::types::PluginType {
+                name: dpt.default_type,
+            },
+            patterns: spec::types::DependencyPatternList {
+                pattern: dpt
+                    .patterns
+                    .into_iter()
+                    .map(spec::types::DependencyPattern::from)
+                    .collect(),
+            },
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct DependencyPattern {
     pub dependencies: Dependency,
@@ -379,11 +689,19 @@ pub struct DependencyPattern {
 impl From<spec::types::DependencyPattern> for DependencyPattern {
     fn from(dp: spec::types::DependencyPattern) -> Self {
         Self {
-            dependencies: Dependency::from(dp.dependencies),
+            dependencies: Dependency::Dependency(DependencyOperator::from(dp.dependencies)),
             typ: dp.typ.name,
         }
     }
 }
+impl From<DependencyPattern> for spec::types::DependencyPattern {
+    fn from(dp: DependencyPattern) -> Self {
+        Self {
+            dependencies: spec::types::ModuleDependency::from(dp.dependencies),
+            typ: spec::types::PluginType { name: dp.typ },
+        }
+    }
+}
 
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct ConditionalInstallPattern {
@@ -393,16 +711,26 @@ pub struct ConditionalInstallPattern {
 impl From<crate::spec::types::ConditionalInstallPattern> for ConditionalInstallPattern {
     fn from(spec: crate::spec::types::ConditionalInstallPattern) -> Self {
         Self {
-            dependencies: Dependency::from(spec.dependencies),
+            dependencies: Dependency::Dependency(DependencyOperator::from(spec.dependencies)),
             files: spec.files.list.unwrap_or_default(),
         }
     }
 }
+impl From<ConditionalInstallPattern> for crate::spec::types::ConditionalInstallPattern {
+    fn from(cip: ConditionalInstallPattern) -> Self {
+        Self {
+            dependencies: crate::spec::types::ModuleDependency::from(cip.dependencies),
+            files: crate::spec::types::FileList {
+                list: Some(cip.files).filter(|fl| !fl.is_empty()),
+            },
+        }
+    }
+}
 
 #[cfg(test)]
 mod tests {
     use crate::spec::Config as SpecConfig;
-    use crate::{Config, Info};
+    use crate::{Config, GroupType, Info, OrderEnum};
 
     #[test]
     pub fn info() {
@@ -696,4 +1024,331 @@ mod tests {
 
         let config = Config::from(config);
     }
+
+    #[test]
+    pub fn natural_order() {
+        use crate::natural_cmp;
+        use std::cmp::Ordering;
+
+        assert_eq!(natural_cmp("Option 9", "Option 10"), Ordering::Less);
+        assert_eq!(natural_cmp("option b", "Option A"), Ordering::Greater);
+        assert_eq!(natural_cmp("Option 01", "Option 1"), Ordering::Less);
+        assert_eq!(natural_cmp("Option", "Option 1"), Ordering::Less);
+        assert_eq!(natural_cmp("Option A", "Option A"), Ordering::Equal);
+    }
+
+    #[test]
+    pub fn sort_order() {
+        let xml = r#"
+        <config xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance"
+            xsi:noNamespaceSchemaLocation="http://qconsulting.ca/fo3/ModConfig5.0.xsd">
+
+            <moduleName>Example Mod</moduleName>
+
+            <installSteps order="Descending">
+                <installStep name="Step 9">
+                    <optionalFileGroups order="Ascending">
+                        <group name="group 10" type="SelectAny">
+                            <plugins order="Descending">
+                                <plugin name="Option 2"><description/></plugin>
+                                <plugin name="option 10"><description/></plugin>
+                                <plugin name="Option 1"><description/></plugin>
+                            </plugins>
+                        </group>
+                        <group name="Group 9" type="SelectAny">
+                            <plugins order="Explicit">
+                                <plugin name="Option 2"><description/></plugin>
+                                <plugin name="option 10"><description/></plugin>
+                                <plugin name="Option 1"><description/></plugin>
+                            </plugins>
+                        </group>
+                    </optionalFileGroups>
+                </installStep>
+                <installStep name="step 10">
+                    <optionalFileGroups order="Explicit">
+                        <group name="Group B" type="SelectAny">
+                            <plugins order="Ascending">
+                                <plugin name="Option 2"><description/></plugin>
+                                <plugin name="option 10"><description/></plugin>
+                                <plugin name="Option 1"><description/></plugin>
+                            </plugins>
+                        </group>
+                        <group name="Group A" type="SelectAny">
+                            <plugins>
+                                <plugin name="b"><description/></plugin>
+                                <plugin name="A"><description/></plugin>
+                            </plugins>
+                        </group>
+                    </optionalFileGroups>
+                </installStep>
+            </installSteps>
+        </config>
+        "#;
+
+        fn names<T>(list: &[T], name: fn(&T) -> &str) -> Vec<&str> {
+            list.iter().map(name).collect()
+        }
+
+        let mut config = Config::try_from(xml).unwrap();
+
+        let steps = config.install_steps.vec_sorted();
+        assert_eq!(names(&steps, |s| &s.name), vec!["step 10", "Step 9"]);
+
+        let groups = steps[1].optional_file_groups.vec_sorted();
+        assert_eq!(names(&groups, |g| &g.name), vec!["Group 9", "group 10"]);
+        let plugins = groups[1].plugins.inner().vec_sorted();
+        assert_eq!(
+            names(&plugins, |p| &p.name),
+            vec!["option 10", "Option 2", "Option 1"]
+        );
+        let plugins = groups[0].plugins.inner().vec_sorted();
+        assert_eq!(
+            names(&plugins, |p| &p.name),
+            vec!["Option 2", "option 10", "Option 1"]
+        );
+
+        let groups = steps[0].optional_file_groups.vec_sorted();
+        assert_eq!(names(&groups, |g| &g.name), vec!["Group B", "Group A"]);
+        let plugins = groups[0].plugins.inner().vec_sorted();
+        assert_eq!(
+            names(&plugins, |p| &p.name),
+            vec!["Option 1", "Option 2", "option 10"]
+        );
+        let plugins = groups[1].plugins.inner().vec_sorted();
+        assert_eq!(names(&plugins, |p| &p.name), vec!["A", "b"]);
+
+        let steps = config.install_steps.vec_sorted_mut();
+        assert_eq!(names(steps, |s| &s.name), vec!["step 10", "Step 9"]);
+    }
+
+    #[test]
+    pub fn write_module_config() {
+        let xml = include_str!("../examples/fomod/ModuleConfig.xml");
+        let config = SpecConfig::try_from(xml).unwrap();
+
+        let written = config.to_xml_string().unwrap();
+        assert!(
+            written.starts_with("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<config xmlns:xsi=")
+        );
+        assert_eq!(SpecConfig::try_from(written.as_str()).unwrap(), config);
+
+        let mut buffer = Vec::new();
+        config.write_to(&mut buffer).unwrap();
+        assert_eq!(String::from_utf8(buffer).unwrap(), written);
+
+        assert_eq!(
+            Config::try_from(written.as_str()).unwrap(),
+            Config::from(config)
+        );
+    }
+
+    #[test]
+    pub fn write_round_trip() {
+        let xml = r#"
+        <config xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance"
+            xsi:noNamespaceSchemaLocation="http://qconsulting.ca/fo3/ModConfig5.0.xsd">
+
+            <moduleName>Example &amp; Mod</moduleName>
+            <moduleImage path="fomod/header.png" showImage="true" height="75"/>
+
+            <moduleDependencies operator="Or">
+                <fileDependency file="depend1.plugin" state="Active"/>
+                <gameDependency version="1.2"/>
+                <fommDependency version="0.13"/>
+                <dependencies operator="And">
+                    <flagDependency flag="a" value=""/>
+                </dependencies>
+            </moduleDependencies>
+
+            <requiredInstallFiles>
+                <file source="core.esp" destination="" priority="2"/>
+                <folder source="core" alwaysInstall="true" installIfUsable="true"/>
+            </requiredInstallFiles>
+
+            <installSteps order="Ascending">
+                <installStep name="Options">
+                    <visible>
+                        <fileDependency file="other.esp" state="Missing"/>
+                    </visible>
+                    <optionalFileGroups order="Descending">
+                        <group name="Options" type="SelectAtMostOne">
+                            <plugins order="Ascending">
+                                <plugin name="Patch">
+                                    <description>A &lt;patch&gt;</description>
+                                    <image path="fomod/patch.png"/>
+                                    <files>
+                                        <file source="patch.esp"/>
+                                    </files>
+                                    <conditionFlags>
+                                        <flag name="patch">on</flag>
+                                    </conditionFlags>
+                                    <typeDescriptor>
+                                        <dependencyType>
+                                            <defaultType name="NotUsable"/>
+                                            <patterns>
+                                                <pattern>
+                                                    <dependencies operator="Or">
+                                                        <fileDependency file="other.esp" state="Active"/>
+                                                        <flagDependency flag="force" value="on"/>
+                                                    </dependencies>
+                                                    <type name="Recommended"/>
+                                                </pattern>
+                                            </patterns>
+                                        </dependencyType>
+                                    </typeDescriptor>
+                                </plugin>
+                            </plugins>
+                        </group>
+                    </optionalFileGroups>
+                </installStep>
+            </installSteps>
+
+            <conditionalFileInstalls>
+                <patterns>
+                    <pattern>
+                        <dependencies operator="And">
+                            <flagDependency flag="patch" value="on"/>
+                        </dependencies>
+                        <files>
+                            <folder source="patch" destination="meshes" priority="-1"/>
+                        </files>
+                    </pattern>
+                </patterns>
+            </conditionalFileInstalls>
+        </config>
+        "#;
+
+        let config = SpecConfig::try_from(xml).unwrap();
+        let written = config.to_xml_string().unwrap();
+        assert_eq!(SpecConfig::try_from(written.as_str()).unwrap(), config);
+        assert!(written.contains(r#"<file source="core.esp" destination="" priority="2"/>"#));
+        assert!(written.contains("<description>A &lt;patch&gt;</description>"));
+        assert!(written.contains(r#"<flag name="patch">on</flag>"#));
+    }
+
+    #[test]
+    pub fn config_to_spec() {
+        let xml = include_str!("../examples/fomod/ModuleConfig.xml");
+        let config = Config::try_from(xml).unwrap();
+
+        let spec = SpecConfig::from(config.clone());
+        assert_eq!(Config::from(spec), config);
+
+        let mut edited = config.clone();
+        edited.module_name = "Edited".to_string();
+        if let OrderEnum::Explicit(steps) = &mut edited.install_steps {
+            steps.remove(1);
+        }
+        let written = SpecConfig::from(edited.clone()).to_xml_string().unwrap();
+        let read = Config::try_from(written.as_str()).unwrap();
+        assert_eq!(read, edited);
+        assert_eq!(read.install_steps.list().len(), 1);
+    }
+
+    #[test]
+    pub fn config_to_spec_dependencies() {
+        use crate::{
+            Dependency, DependencyOperator, DependencyPattern, DependencyPluginType,
+            PluginTypeDescriptorEnum, PluginTypeEnum,
+        };
+
+        let xml = r#"
+        <config xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance"
+            xsi:noNamespaceSchemaLocation="http://qconsulting.ca/fo3/ModConfig5.0.xsd">
+
+            <moduleName>Example Mod</moduleName>
+
+            <moduleDependencies operator="Or">
+                <fileDependency file="depend1.plugin" state="Active"/>
+                <dependencies operator="And">
+                    <gameDependency version="1.2"/>
+                    <fommDependency version="0.13"/>
+                </dependencies>
+            </moduleDependencies>
+
+            <installSteps order="Descending">
+                <installStep name="Options">
+                    <visible>
+                        <flagDependency flag="a" value="on"/>
+                    </visible>
+                    <optionalFileGroups order="Ascending">
+                        <group name="Options" type="SelectAtMostOne">
+                            <plugins order="Explicit">
+                                <plugin name="Patch">
+                                    <description>Patch</description>
+                                    <image path="fomod/patch.png"/>
+                                    <files>
+                                        <file source="patch.esp" priority="1"/>
+                                    </files>
+                                    <conditionFlags>
+                                        <flag name="patch">on</flag>
+                                    </conditionFlags>
+                                    <typeDescriptor>
+                                        <dependencyType>
+                                            <defaultType name="NotUsable"/>
+                                            <patterns>
+                                                <pattern>
+                                                    <dependencies operator="Or">
+                                                        <fileDependency file="other.esp" state="Active"/>
+                                                    </dependencies>
+                                                    <type name="Recommended"/>
+                                                </pattern>
+                                            </patterns>
+                                        </dependencyType>
+                                    </typeDescriptor>
+                                </plugin>
+                            </plugins>
+                        </group>
+                    </optionalFileGroups>
+                </installStep>
+            </installSteps>
+
+            <conditionalFileInstalls>
+                <patterns>
+                    <pattern>
+                        <dependencies operator="And">
+                            <flagDependency flag="patch" value="on"/>
+                        </dependencies>
+                        <files>
+                            <folder source="patch" destination=""/>
+                        </files>
+                    </pattern>
+                </patterns>
+            </conditionalFileInstalls>
+        </config>
+        "#;
+
+        let config = Config::try_from(xml).unwrap();
+        let written = SpecConfig::from(config.clone()).to_xml_string().unwrap();
+        assert_eq!(Config::try_from(written.as_str()).unwrap(), config);
+
+        let mut edited = config;
+        if let OrderEnum::Descending(steps) = &mut edited.install_steps {
+            let group = &mut steps[0].optional_file_groups.vec_sorted_mut()[0];
+            let GroupType::SelectAtMostOne(plugins) = &mut group.plugins else {
+                panic!("unexpected group type");
+            };
+            plugins.vec_sorted_mut()[0].type_descriptor = Some(
+                PluginTypeDescriptorEnum::DependencyType(DependencyPluginType {
+                    default_type: PluginTypeEnum::Optional,
+                    patterns: vec![DependencyPattern {
+                        dependencies: Dependency::Dependency(DependencyOperator::And(vec![])),
+                        typ: PluginTypeEnum::Required,
+                    }],
+                }),
+            );
+        }
+        let written = SpecConfig::from(edited.clone()).to_xml_string().unwrap();
+        assert!(written.contains(r#"<defaultType name="Optional"/>"#));
+        assert_eq!(Config::try_from(written.as_str()).unwrap(), edited);
+    }
+
+    #[test]
+    pub fn write_info() {
+        let xml = include_str!("../examples/fomod/info.xml");
+        let info = Info::try_from(xml).unwrap();
+
+        let written = info.to_xml_string().unwrap();
+        assert_eq!(Info::try_from(written.as_str()).unwrap(), info);
+    }
 }
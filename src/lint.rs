@@ -0,0 +1,565 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, ptr,
+    str::FromStr,
+};
+
+use crate::{
+    error::{locate, Step},
+    Config, Dependency, DependencyOperator, FlagDependency, FlagFlow, FlagRead, GroupType,
+    InstallStep, Plugin, PluginTypeDescriptorEnum, Position,
+};
+
+/// The kind of a lint finding; the code is stable, so it can be used to
+/// suppress findings.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LintCode {
+    /// A flag is read with a value which no plugin ever sets.
+    UnsetFlag,
+    /// A flag is set by a plugin, but never read.
+    UnreadFlag,
+    /// A group without plugins.
+    EmptyGroup,
+    /// A group which needs a selection, but where every plugin is `NotUsable`.
+    UnusableGroup,
+    /// A step with the name of an earlier step.
+    DuplicateStep,
+    /// A group with the name of an earlier group of the same step.
+    DuplicateGroup,
+    /// A plugin with the name of an earlier plugin of the same group.
+    DuplicatePlugin,
+    /// A pattern whose dependencies can never hold.
+    ImpossiblePattern,
+    /// A step whose `visible` condition can never hold.
+    UnreachableStep,
+    /// A flag value is read by a step, but only set by the step itself or a
+    /// later one.
+    LateFlag,
+}
+impl LintCode {
+    pub const ALL: [Self; 10] = [
+        Self::UnsetFlag,
+        Self::UnreadFlag,
+        Self::EmptyGroup,
+        Self::UnusableGroup,
+        Self::DuplicateStep,
+        Self::DuplicateGroup,
+        Self::DuplicatePlugin,
+        Self::ImpossiblePattern,
+        Self::UnreachableStep,
+        Self::LateFlag,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::UnsetFlag => "unset-flag",
+            Self::UnreadFlag => "unread-flag",
+            Self::EmptyGroup => "empty-group",
+            Self::UnusableGroup => "unusable-group",
+            Self::DuplicateStep => "duplicate-step",
+            Self::DuplicateGroup => "duplicate-group",
+            Self::DuplicatePlugin => "duplicate-plugin",
+            Self::ImpossiblePattern => "impossible-pattern",
+            Self::UnreachableStep => "unreachable-step",
+            Self::LateFlag => "late-flag",
+        }
+    }
+}
+impl fmt::Display for LintCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+impl FromStr for LintCode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|code| code.as_str() == s)
+            .ok_or_else(|| format!("unknown lint code '{s}'"))
+    }
+}
+
+/// A likely mistake in a `Config`, which is valid nonetheless.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Lint {
+    pub code: LintCode,
+    pub message: String,
+    /// The element path, e.g. `installSteps/installStep[2]/optionalFileGroups/group[1]`.
+    pub path: String,
+    steps: Vec<Step>,
+}
+impl Lint {
+    fn new(code: LintCode, steps: &[Step], message: String) -> Self {
+        let path = steps
+            .iter()
+            .map(|step| match step {
+                Step::Child(name, None) => name.clone(),
+                Step::Child(name, Some(nth)) => format!("{name}[{}]", nth + 1),
+                Step::Any(nth) => format!("*[{}]", nth + 1),
+                Step::Attribute(name) => format!("@{name}"),
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+        Self {
+            code,
+            message,
+            path,
+            steps: steps.to_vec(),
+        }
+    }
+
+    /// The position of the element in the document the config was read from.
+    pub fn position(&self, xml: &str) -> Option<Position> {
+        locate(xml, &self.steps).1
+    }
+}
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.path.is_empty() {
+            write!(f, "{}: ", self.path)?;
+        }
+        write!(f, "{} [{}]", self.message, self.code)
+    }
+}
+
+/// Looks for likely mistakes which deserialization does not catch, in
+/// document order.
+pub fn lint(config: &Config) -> Vec<Lint> {
+    let mut walk = Walk::default();
+    walk.config(config);
+
+    let mut lints = walk.lints;
+    for (steps, fd) in &walk.reads {
+        if fd.value.is_empty() {
+            continue;
+        }
+        let message = match walk.set.get(&fd.flag) {
+            None => format!("flag '{}' is never set", fd.flag),
+            Some(values) if !values.contains(&fd.value) => {
+                format!("flag '{}' is never set to '{}'", fd.flag, fd.value)
+            }
+            Some(_) => continue,
+        };
+        lints.push(Lint::new(LintCode::UnsetFlag, steps, message));
+    }
+
+    let read = walk
+        .reads
+        .iter()
+        .map(|(_, fd)| fd.flag.as_str())
+        .collect::<HashSet<_>>();
+    for (steps, flag) in &walk.sets {
+        if !read.contains(flag.as_str()) {
+            let message = format!("flag '{flag}' is never read");
+            lints.push(Lint::new(LintCode::UnreadFlag, steps, message));
+        }
+    }
+
+    let flow = FlagFlow::new(config);
+    for (steps, dependency) in &walk.patterns {
+        if !flow.after().may_hold(dependency) {
+            let message = "the dependencies of this pattern can never hold".to_string();
+            lints.push(Lint::new(LintCode::ImpossiblePattern, steps, message));
+        }
+    }
+
+    let steps = config.install_steps.list();
+    let step_steps = |step: &InstallStep| {
+        let i = steps.iter().position(|s| ptr::eq(s, step));
+        vec![child("installSteps", None), child("installStep", i)]
+    };
+    for step in flow.unreachable_steps() {
+        let steps = with(&step_steps(step), [child("visible", None)]);
+        let message = format!("step '{}' can never be visible", step.name);
+        lints.push(Lint::new(LintCode::UnreachableStep, &steps, message));
+    }
+    for late in flow.late_flags() {
+        let steps = match late.read {
+            FlagRead::Visible => with(&step_steps(late.step), [child("visible", None)]),
+            FlagRead::PluginType { group, plugin } => {
+                let groups = late.step.optional_file_groups.list();
+                let j = groups.iter().position(|g| ptr::eq(g, group));
+                let k = group
+                    .plugins
+                    .inner()
+                    .list()
+                    .iter()
+                    .position(|p| ptr::eq(p, plugin));
+                with(
+                    &step_steps(late.step),
+                    [
+                        child("optionalFileGroups", None),
+                        child("group", j),
+                        child("plugins", None),
+                        child("plugin", k),
+                        child("typeDescriptor", None),
+                    ],
+                )
+            }
+        };
+        let message = format!(
+            "flag '{}' is set to '{}' only by step '{}', which comes later",
+            late.flag, late.value, late.set_by.name
+        );
+        lints.push(Lint::new(LintCode::LateFlag, &steps, message));
+    }
+
+    lints.sort_by_cached_key(|lint| order(&lint.steps));
+    lints
+}
+
+/// A sort key for document order; siblings with different names come in the
+/// order of the schema.
+fn order(steps: &[Step]) -> Vec<(usize, usize)> {
+    const ELEMENTS: [&str; 8] = [
+        "moduleDependencies",
+        "installSteps",
+        "conditionalFileInstalls",
+        "visible",
+        "optionalFileGroups",
+        "conditionFlags",
+        "typeDescriptor",
+        "dependencies",
+    ];
+    steps
+        .iter()
+        .map(|step| match step {
+            Step::Child(name, nth) => (
+                ELEMENTS.iter().position(|e| e == name).unwrap_or(0),
+                nth.unwrap_or(0),
+            ),
+            Step::Any(nth) => (0, *nth),
+            Step::Attribute(_) => (0, 0),
+        })
+        .collect()
+}
+
+#[derive(Default)]
+struct Walk<'a> {
+    lints: Vec<Lint>,
+    /// Every flag dependency, where it is read.
+    reads: Vec<(Vec<Step>, &'a FlagDependency)>,
+    /// Every flag set by a plugin, where it is set.
+    sets: Vec<(Vec<Step>, String)>,
+    /// The values every flag can be set to.
+    set: HashMap<String, HashSet<String>>,
+    patterns: Vec<(Vec<Step>, &'a Dependency)>,
+}
+impl<'a> Walk<'a> {
+    fn config(&mut self, config: &'a Config) {
+        if let Some(DependencyOperator::And(list) | DependencyOperator::Or(list)) =
+            &config.module_dependencies
+        {
+            let steps = vec![child("moduleDependencies", None)];
+            for dependency in list {
+                self.reads(&steps, dependency);
+            }
+        }
+
+        let mut names = HashSet::new();
+        for (i, step) in config.install_steps.list().iter().enumerate() {
+            let steps = vec![child("installSteps", None), child("installStep", Some(i))];
+            if !names.insert(&step.name) {
+                let message = format!("step '{}' appears more than once", step.name);
+                self.lints
+                    .push(Lint::new(LintCode::DuplicateStep, &steps, message));
+            }
+            if let Some(visible) = &step.visible {
+                self.reads(&with(&steps, [child("visible", None)]), visible);
+            }
+
+            let mut groups = HashSet::new();
+            for (j, group) in step.optional_file_groups.list().iter().enumerate() {
+                let steps = with(
+                    &steps,
+                    [child("optionalFileGroups", None), child("group", Some(j))],
+                );
+                if !groups.insert(&group.name) {
+                    let message = format!("group '{}' appears more than once", group.name);
+                    self.lints
+                        .push(Lint::new(LintCode::DuplicateGroup, &steps, message));
+                }
+                self.group(&steps, &group.name, &group.plugins);
+            }
+        }
+
+        for (i, pattern) in config.conditional_file_installs.iter().enumerate() {
+            let steps = vec![
+                child("conditionalFileInstalls", None),
+                child("patterns", None),
+                child("pattern", Some(i)),
+            ];
+            self.reads(
+                &with(&steps, [child("dependencies", None)]),
+                &pattern.dependencies,
+            );
+            self.patterns.push((steps, &pattern.dependencies));
+        }
+    }
+
+    fn group(
+        &mut self,
+        steps: &[Step],
+        name: &str,
+        group: &'a GroupType<crate::OrderEnum<Plugin>>,
+    ) {
+        let plugins = group.inner().list();
+        if plugins.is_empty() {
+            let message = format!("group '{name}' has no plugins");
+            self.lints
+                .push(Lint::new(LintCode::EmptyGroup, steps, message));
+        } else if matches!(
+            group,
+            GroupType::SelectExactlyOne(_) | GroupType::SelectAtLeastOne(_)
+        ) && plugins.iter().all(Plugin::is_never_usable)
+        {
+            let message = format!("group '{name}' needs a selection, but no plugin is usable");
+            self.lints
+                .push(Lint::new(LintCode::UnusableGroup, steps, message));
+        }
+
+        let mut names = HashSet::new();
+        for (k, plugin) in plugins.iter().enumerate() {
+            let steps = with(steps, [child("plugins", None), child("plugin", Some(k))]);
+            if !names.insert(&plugin.name) {
+                let message = format!("plugin '{}' appears more than once", plugin.name);
+                self.lints
+                    .push(Lint::new(LintCode::DuplicatePlugin, &steps, message));
+            }
+
+            for (l, fd) in plugin.condition_flags.iter().enumerate() {
+                let steps = with(
+                    &steps,
+                    [child("conditionFlags", None), child("flag", Some(l))],
+                );
+                self.set
+                    .entry(fd.flag.clone())
+                    .or_default()
+                    .insert(fd.value.clone());
+                self.sets.push((steps, fd.flag.clone()));
+            }
+
+            if let Some(PluginTypeDescriptorEnum::DependencyType(dt)) = &plugin.type_descriptor {
+                for (l, pattern) in dt.patterns.iter().enumerate() {
+                    let steps = with(
+                        &steps,
+                        [
+                            child("typeDescriptor", None),
+                            child("dependencyType", None),
+                            child("patterns", None),
+                            child("pattern", Some(l)),
+                        ],
+                    );
+                    self.reads(
+                        &with(&steps, [child("dependencies", None)]),
+                        &pattern.dependencies,
+                    );
+                    self.patterns.push((steps, &pattern.dependencies));
+                }
+            }
+        }
+    }
+
+    /// Records the flag dependencies in `dependency`.
+    fn reads(&mut self, steps: &[Step], dependency: &'a Dependency) {
+        match dependency {
+            Dependency::Flag(fd) => self.reads.push((steps.to_vec(), fd)),
+            Dependency::Dependency(
+                DependencyOperator::And(list) | DependencyOperator::Or(list),
+            ) => {
+                for dependency in list {
+                    self.reads(steps, dependency);
+                }
+            }
+            Dependency::File(_) | Dependency::Game(_) | Dependency::Fomm(_) => {}
+        }
+    }
+}
+
+fn child(name: &str, nth: Option<usize>) -> Step {
+    Step::Child(name.to_string(), nth)
+}
+
+fn with<const N: usize>(steps: &[Step], more: [Step; N]) -> Vec<Step> {
+    let mut steps = steps.to_vec();
+    steps.extend(more);
+    steps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{lint, LintCode};
+    use crate::{Config, Group, GroupType, OrderEnum, Position};
+
+    const XML: &str = r#"<config>
+    <moduleName>Example Mod</moduleName>
+    <installSteps order="Explicit">
+        <installStep name="Options">
+            <visible><flagDependency flag="never" value="On"/></visible>
+            <optionalFileGroups order="Explicit">
+                <group name="Textures" type="SelectExactlyOne">
+                    <plugins order="Explicit">
+                        <plugin name="HD">
+                            <description/>
+                            <conditionFlags>
+                                <flag name="hd">On</flag>
+                                <flag name="unused">On</flag>
+                            </conditionFlags>
+                            <typeDescriptor><type name="NotUsable"/></typeDescriptor>
+                        </plugin>
+                        <plugin name="HD">
+                            <description/>
+                            <typeDescriptor>
+                                <dependencyType>
+                                    <defaultType name="NotUsable"/>
+                                    <patterns>
+                                        <pattern>
+                                            <dependencies><flagDependency flag="hd" value="On"/></dependencies>
+                                            <type name="NotUsable"/>
+                                        </pattern>
+                                    </patterns>
+                                </dependencyType>
+                            </typeDescriptor>
+                        </plugin>
+                    </plugins>
+                </group>
+            </optionalFileGroups>
+        </installStep>
+        <installStep name="Options">
+            <optionalFileGroups order="Explicit">
+                <group name="Other" type="SelectAny">
+                    <plugins order="Explicit"><plugin name="Other"><description/></plugin></plugins>
+                </group>
+            </optionalFileGroups>
+        </installStep>
+    </installSteps>
+    <conditionalFileInstalls>
+        <patterns>
+            <pattern>
+                <dependencies>
+                    <flagDependency flag="hd" value="On"/>
+                    <flagDependency flag="hd" value="Off"/>
+                </dependencies>
+                <files/>
+            </pattern>
+            <pattern>
+                <dependencies operator="Or">
+                    <flagDependency flag="hd" value="On"/>
+                    <flagDependency flag="hd" value=""/>
+                </dependencies>
+                <files/>
+            </pattern>
+        </patterns>
+    </conditionalFileInstalls>
+</config>"#;
+
+    #[test]
+    pub fn lints() {
+        let mut config = Config::try_from(XML).unwrap();
+        // The schema does not allow empty groups, but a config built in code can have them.
+        let groups = match &mut config.install_steps {
+            OrderEnum::Explicit(steps) => match &mut steps[0].optional_file_groups {
+                OrderEnum::Explicit(groups) => groups,
+                _ => unreachable!(),
+            },
+            _ => unreachable!(),
+        };
+        groups.push(Group {
+            name: "Empty".to_string(),
+            plugins: GroupType::SelectAny(OrderEnum::Explicit(Vec::new())),
+        });
+        let lints = lint(&config);
+        let found = lints
+            .iter()
+            .map(|lint| (lint.code, lint.path.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            vec![
+                (LintCode::UnsetFlag, "installSteps/installStep[1]/visible"),
+                (LintCode::UnreachableStep, "installSteps/installStep[1]/visible"),
+                (
+                    LintCode::UnusableGroup,
+                    "installSteps/installStep[1]/optionalFileGroups/group[1]"
+                ),
+                (
+                    LintCode::UnreadFlag,
+                    "installSteps/installStep[1]/optionalFileGroups/group[1]/plugins/plugin[1]/conditionFlags/flag[2]"
+                ),
+                (
+                    LintCode::DuplicatePlugin,
+                    "installSteps/installStep[1]/optionalFileGroups/group[1]/plugins/plugin[2]"
+                ),
+                (
+                    LintCode::ImpossiblePattern,
+                    "installSteps/installStep[1]/optionalFileGroups/group[1]/plugins/plugin[2]/typeDescriptor/dependencyType/patterns/pattern[1]"
+                ),
+                (
+                    LintCode::EmptyGroup,
+                    "installSteps/installStep[1]/optionalFileGroups/group[2]"
+                ),
+                (LintCode::DuplicateStep, "installSteps/installStep[2]"),
+                (
+                    LintCode::ImpossiblePattern,
+                    "conditionalFileInstalls/patterns/pattern[1]"
+                ),
+                (
+                    LintCode::UnsetFlag,
+                    "conditionalFileInstalls/patterns/pattern[1]/dependencies"
+                ),
+            ]
+        );
+
+        assert_eq!(
+            lints[4].to_string(),
+            "installSteps/installStep[1]/optionalFileGroups/group[1]/plugins/plugin[2]: \
+             plugin 'HD' appears more than once [duplicate-plugin]"
+        );
+        assert_eq!(
+            lints[4].position(XML),
+            Some(Position {
+                line: 17,
+                column: 25
+            })
+        );
+        assert_eq!("empty-group".parse(), Ok(LintCode::EmptyGroup));
+        assert!("other".parse::<LintCode>().is_err());
+    }
+
+    #[test]
+    pub fn duplicate_groups() {
+        let xml = r#"<config>
+    <moduleName>Example Mod</moduleName>
+    <installSteps order="Explicit">
+        <installStep name="Options">
+            <optionalFileGroups order="Explicit">
+                <group name="Textures" type="SelectAny">
+                    <plugins order="Explicit"><plugin name="HD"><description/></plugin></plugins>
+                </group>
+                <group name="Textures" type="SelectAny">
+                    <plugins order="Explicit"><plugin name="SD"><description/></plugin></plugins>
+                </group>
+            </optionalFileGroups>
+        </installStep>
+    </installSteps>
+</config>"#;
+        let lints = lint(&Config::try_from(xml).unwrap());
+
+        assert_eq!(lints.len(), 1);
+        assert_eq!(lints[0].code, LintCode::DuplicateGroup);
+        assert_eq!(
+            lints[0].to_string(),
+            "installSteps/installStep[1]/optionalFileGroups/group[2]: \
+             group 'Textures' appears more than once [duplicate-group]"
+        );
+        assert_eq!(
+            lints[0].position(xml),
+            Some(Position {
+                line: 9,
+                column: 17
+            })
+        );
+    }
+}
@@ -0,0 +1,298 @@
+use std::{
+    fs,
+    io::{self, BufReader},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{path::normalize, spec, Error, FileOrigin, InstallOperation};
+
+/// The files written by an installation.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct InstallManifest {
+    #[serde(rename = "@moduleName")]
+    pub module_name: String,
+    #[serde(rename = "file", default)]
+    pub files: Vec<ManifestEntry>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct ManifestEntry {
+    /// Path relative to the installation root.
+    #[serde(rename = "@destination")]
+    pub destination: String,
+    /// Path relative to the package root.
+    #[serde(rename = "@source")]
+    pub source: String,
+    #[serde(rename = "@size")]
+    pub size: u64,
+    /// Hex encoded SHA-256 of the contents.
+    #[serde(rename = "@sha256")]
+    pub sha256: String,
+    #[serde(rename = "$value", with = "origin")]
+    pub origin: FileOrigin,
+}
+impl ManifestEntry {
+    pub fn new(operation: &InstallOperation, contents: &[u8]) -> Self {
+        Self {
+            destination: operation.destination.clone(),
+            source: operation.source.clone(),
+            size: contents.len() as u64,
+            sha256: sha256(contents),
+            origin: operation.origin.clone(),
+        }
+    }
+    /// Whether `contents` are the installed file.
+    pub fn matches(&self, contents: &[u8]) -> bool {
+        contents.len() as u64 == self.size && sha256(contents) == self.sha256
+    }
+}
+
+/// A difference between a manifest and the installed files.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ManifestMismatch {
+    Missing(String),
+    Modified(String),
+}
+
+impl InstallManifest {
+    pub fn to_xml_string(&self) -> Result<String, Error> {
+        spec::to_xml_string(self, "manifest", &[])
+    }
+    pub fn write_to<W: io::Write>(&self, writer: W) -> Result<(), Error> {
+        spec::write_to(writer, &self.to_xml_string()?)
+    }
+
+    /// Compares the installed files in `target` with the manifest.
+    pub fn verify(&self, target: impl AsRef<Path>) -> Result<Vec<ManifestMismatch>, Error> {
+        let target = target.as_ref();
+        let mut mismatches = Vec::new();
+        for entry in &self.files {
+            let path = target.join(normalize(&entry.destination)?);
+            match fs::read(&path) {
+                Ok(contents) => {
+                    if !entry.matches(&contents) {
+                        mismatches.push(ManifestMismatch::Modified(entry.destination.clone()));
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                    mismatches.push(ManifestMismatch::Missing(entry.destination.clone()))
+                }
+                Err(e) => return Err(Error::from(e).with_file(path)),
+            }
+        }
+        Ok(mismatches)
+    }
+
+    /// Removes the installed files from `target`, and the folders left empty.
+    ///
+    /// Returns the destinations which were removed. Missing files are skipped,
+    /// and so are modified ones, e.g. overwritten by another mod since; `verify`
+    /// lists those.
+    pub fn uninstall(&self, target: impl AsRef<Path>) -> Result<Vec<String>, Error> {
+        let target = target.as_ref();
+        let mut removed = Vec::new();
+        for entry in &self.files {
+            let destination = normalize(&entry.destination)?;
+            let path = target.join(&destination);
+            match fs::read(&path) {
+                Ok(contents) if entry.matches(&contents) => {}
+                Ok(_) => continue,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(Error::from(e).with_file(path)),
+            }
+            fs::remove_file(&path).map_err(|e| Error::from(e).with_file(&path))?;
+            removed.push(entry.destination.clone());
+
+            let mut dir = Path::new(&destination).parent();
+            while let Some(parent) = dir.filter(|p| !p.as_os_str().is_empty()) {
+                if fs::remove_dir(target.join(parent)).is_err() {
+                    break;
+                }
+                dir = parent.parent();
+            }
+        }
+        Ok(removed)
+    }
+}
+impl TryFrom<&str> for InstallManifest {
+    type Error = Error;
+
+    fn try_from(string: &str) -> Result<Self, Self::Error> {
+        spec::from_str(string)
+    }
+}
+impl TryFrom<&[u8]> for InstallManifest {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        spec::from_str(&spec::decode(bytes)?)
+    }
+}
+impl<T> TryFrom<BufReader<T>> for InstallManifest
+where
+    T: io::Read,
+{
+    type Error = Error;
+
+    fn try_from(reader: BufReader<T>) -> Result<Self, Self::Error> {
+        Self::try_from(spec::read_all(reader)?.as_slice())
+    }
+}
+
+fn sha256(contents: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(contents))
+}
+
+mod origin {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::FileOrigin;
+
+    #[derive(Deserialize, Serialize)]
+    enum Origin {
+        #[serde(rename = "required")]
+        Required,
+        #[serde(rename = "plugin")]
+        Plugin {
+            #[serde(rename = "@step")]
+            step: String,
+            #[serde(rename = "@group")]
+            group: String,
+            #[serde(rename = "@name")]
+            plugin: String,
+        },
+        #[serde(rename = "conditional")]
+        Conditional {
+            #[serde(rename = "@index")]
+            index: usize,
+        },
+    }
+
+    pub fn serialize<S: Serializer>(origin: &FileOrigin, serializer: S) -> Result<S::Ok, S::Error> {
+        match origin.clone() {
+            FileOrigin::Required => Origin::Required,
+            FileOrigin::Plugin {
+                step,
+                group,
+                plugin,
+            } => Origin::Plugin {
+                step,
+                group,
+                plugin,
+            },
+            FileOrigin::Conditional(index) => Origin::Conditional { index },
+        }
+        .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<FileOrigin, D::Error> {
+        Ok(match Origin::deserialize(deserializer)? {
+            Origin::Required => FileOrigin::Required,
+            Origin::Plugin {
+                step,
+                group,
+                plugin,
+            } => FileOrigin::Plugin {
+                step,
+                group,
+                plugin,
+            },
+            Origin::Conditional { index } => FileOrigin::Conditional(index),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{InstallManifest, ManifestMismatch};
+    use crate::{
+        Config, FileOrigin, FomodPackage, InstallerSession, MemoryEnvironment, MemorySource,
+    };
+
+    const CONFIG: &str = r#"
+        <config>
+            <moduleName>Example Mod</moduleName>
+            <requiredInstallFiles>
+                <file source="core.esp" destination=""/>
+            </requiredInstallFiles>
+            <installSteps order="Explicit">
+                <installStep name="Options">
+                    <optionalFileGroups order="Explicit">
+                        <group name="Textures" type="SelectExactlyOne">
+                            <plugins order="Explicit">
+                                <plugin name="HD">
+                                    <description/>
+                                    <files><folder source="hd" destination="textures"/></files>
+                                    <conditionFlags><flag name="hd">On</flag></conditionFlags>
+                                </plugin>
+                            </plugins>
+                        </group>
+                    </optionalFileGroups>
+                </installStep>
+            </installSteps>
+            <conditionalFileInstalls>
+                <patterns>
+                    <pattern>
+                        <dependencies><flagDependency flag="hd" value="On"/></dependencies>
+                        <files><file source="hd.ini" destination="hd.ini"/></files>
+                    </pattern>
+                </patterns>
+            </conditionalFileInstalls>
+        </config>
+        "#;
+
+    #[test]
+    pub fn manifests() {
+        let mut package = FomodPackage::new(
+            MemorySource::new()
+                .with_file("fomod/ModuleConfig.xml", CONFIG)
+                .with_file("core.esp", "esp")
+                .with_file("hd/a.dds", "a")
+                .with_file("hd/sub/b.dds", "b")
+                .with_file("hd.ini", "ini"),
+        )
+        .unwrap();
+        let config = Config::try_from(CONFIG).unwrap();
+        let env = MemoryEnvironment::new();
+        let mut session = InstallerSession::new(&config, &env);
+        session.next().unwrap();
+
+        let target = std::env::temp_dir().join(format!("fomod-manifest-{}", std::process::id()));
+        let manifest = package.install(&session.install_plan(), &target).unwrap();
+
+        let xml = manifest.to_xml_string().unwrap();
+        let loaded = InstallManifest::try_from(xml.as_str()).unwrap();
+        assert_eq!(loaded, manifest);
+        assert_eq!(loaded.files[0].size, 3);
+        assert_eq!(
+            loaded.files[0].sha256,
+            "f54344a0d2ec50a9cbdf6f91af95f9d69d729e839de4f24164952b7d59d4d7ba"
+        );
+        assert_eq!(loaded.files[3].origin, FileOrigin::Conditional(0));
+
+        fs::write(target.join("textures/a.dds"), "changed").unwrap();
+        fs::remove_file(target.join("hd.ini")).unwrap();
+        let mismatches = loaded.verify(&target);
+        let removed = loaded.uninstall(&target);
+        let leftover = fs::read_dir(&target).map(|dir| dir.count());
+        let kept = fs::read(target.join("textures/a.dds"));
+        fs::remove_dir_all(&target).unwrap();
+
+        assert_eq!(
+            mismatches.unwrap(),
+            vec![
+                ManifestMismatch::Modified("textures/a.dds".to_string()),
+                ManifestMismatch::Missing("hd.ini".to_string())
+            ]
+        );
+        // The modified file may belong to another mod now.
+        assert_eq!(removed.unwrap(), vec!["core.esp", "textures/sub/b.dds"]);
+        assert_eq!(leftover.unwrap(), 1);
+        assert_eq!(kept.unwrap(), b"changed");
+    }
+}
@@ -0,0 +1,397 @@
+mod directory;
+mod memory;
+#[cfg(feature = "sevenz")]
+mod sevenz;
+#[cfg(feature = "tar")]
+mod tar;
+#[cfg(feature = "zip")]
+mod zip;
+
+#[cfg(feature = "zip")]
+pub use self::zip::ZipSource;
+pub use self::{directory::DirSource, memory::MemorySource};
+
+use std::{collections::BTreeMap, io, path::Path};
+
+use crate::{
+    path::{normalize, PathNormalizer},
+    Config, Error, FileTypeEnum, Info,
+};
+
+/// The largest file read from an archive, whatever size its header claims;
+/// Bethesda archives cannot be larger either.
+#[cfg(feature = "zip")]
+pub(crate) const MAX_FILE_SIZE: u64 = 4 << 30;
+
+/// The most unpacked into memory from a 7z or tar archive, across all of its
+/// files, whatever sizes their headers claim.
+#[cfg(any(feature = "sevenz", feature = "tar"))]
+pub(crate) const MAX_UNPACKED_SIZE: u64 = 4 << 30;
+
+/// Reads a file of an archive, failing once it is larger than `limit`.
+#[cfg(feature = "zip")]
+pub(crate) fn read_limited(reader: impl io::Read, limit: u64) -> io::Result<Vec<u8>> {
+    use io::Read;
+
+    let mut bytes = Vec::new();
+    reader.take(limit + 1).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 > limit {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("file is larger than {limit} bytes"),
+        ));
+    }
+    Ok(bytes)
+}
+
+/// Reads the files of an archive, failing once together they are larger
+/// than a limit.
+#[cfg(any(feature = "sevenz", feature = "tar"))]
+pub(crate) struct UnpackLimit {
+    limit: u64,
+    left: u64,
+}
+#[cfg(any(feature = "sevenz", feature = "tar"))]
+impl UnpackLimit {
+    pub(crate) fn new(limit: u64) -> Self {
+        Self { limit, left: limit }
+    }
+
+    pub(crate) fn read(&mut self, reader: impl io::Read) -> io::Result<Vec<u8>> {
+        use io::Read;
+
+        let mut bytes = Vec::new();
+        reader.take(self.left + 1).read_to_end(&mut bytes)?;
+        self.left = self.left.checked_sub(bytes.len() as u64).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("archive unpacks to more than {} bytes", self.limit),
+            )
+        })?;
+        Ok(bytes)
+    }
+}
+
+/// Where the files of a fomod package are read from.
+pub trait PackageSource {
+    /// The paths of all files, with `/` as separator.
+    fn entries(&self) -> Vec<String>;
+    /// Reads a file, given by a path as returned by `entries`.
+    fn read(&mut self, path: &str) -> Result<Vec<u8>, Error>;
+}
+impl<T: PackageSource + ?Sized> PackageSource for Box<T> {
+    fn entries(&self) -> Vec<String> {
+        (**self).entries()
+    }
+    fn read(&mut self, path: &str) -> Result<Vec<u8>, Error> {
+        (**self).read(path)
+    }
+}
+
+/// A mod with a `fomod` directory, and the files it installs from.
+///
+/// The `fomod` directory is found case-insensitively, and may be nested in a
+/// wrapper folder; the folder containing it is the root of the package.
+pub struct FomodPackage<S> {
+    source: S,
+    root: String,
+    /// Lowercased paths relative to the root, to paths in the source.
+    files: BTreeMap<String, String>,
+    info: Option<Info>,
+    config: Config,
+    paths: PathNormalizer,
+}
+impl<S: PackageSource> FomodPackage<S> {
+    pub fn new(mut source: S) -> Result<Self, Error> {
+        let entries = source.entries();
+        let root = find_root(&entries).ok_or_else(|| {
+            Error::Io(io::Error::new(
+                io::ErrorKind::NotFound,
+                "no fomod/ModuleConfig.xml found",
+            ))
+        })?;
+
+        let files = entries
+            .into_iter()
+            .filter(|entry| entry.len() > root.len() && entry.is_char_boundary(root.len()))
+            .filter(|entry| entry[..root.len()].eq_ignore_ascii_case(&root))
+            .map(|entry| (entry[root.len()..].to_lowercase(), entry))
+            .collect::<BTreeMap<_, _>>();
+
+        let mut read = |name: &str| match files.get(name) {
+            Some(path) => source
+                .read(path)
+                .map(|bytes| Some((path.clone(), bytes)))
+                .map_err(|e| e.with_file(path)),
+            None => Ok(None),
+        };
+        let info = read(INFO)?
+            .map(|(path, bytes)| Info::try_from(bytes.as_slice()).map_err(|e| e.with_file(path)))
+            .transpose()?;
+        let config = read(MODULE_CONFIG)?
+            .map(|(path, bytes)| Config::try_from(bytes.as_slice()).map_err(|e| e.with_file(path)))
+            .transpose()?
+            .expect("the root is where ModuleConfig.xml is");
+
+        Ok(Self {
+            source,
+            root,
+            files,
+            info,
+            config,
+            paths: PathNormalizer::default(),
+        })
+    }
+
+    /// Reads a file of the package, by a path relative to the root.
+    ///
+    /// The path is matched case-insensitively and may use `\` as separator.
+    pub fn read(&mut self, path: &str) -> Result<Vec<u8>, Error> {
+        let key = normalize(path)
+            .map_err(|e| Error::from(e).with_file(path))?
+            .to_lowercase();
+        let entry = self.files.get(&key).ok_or_else(|| {
+            Error::Io(io::Error::new(io::ErrorKind::NotFound, "file not found")).with_file(path)
+        })?;
+        self.source.read(entry).map_err(|e| e.with_file(entry))
+    }
+}
+impl FomodPackage<Box<dyn PackageSource>> {
+    /// Opens a directory, or an archive of any of the enabled formats by its
+    /// extension.
+    #[cfg_attr(
+        not(any(feature = "zip", feature = "sevenz", feature = "tar")),
+        allow(unused_variables)
+    )]
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let name = path.to_string_lossy().to_lowercase();
+
+        let source: Option<Box<dyn PackageSource>> = match () {
+            _ if path.is_dir() => Some(Box::new(DirSource::open(path)?)),
+            #[cfg(feature = "zip")]
+            _ if name.ends_with(".zip") => Some(Box::new(ZipSource::open(path)?)),
+            #[cfg(feature = "sevenz")]
+            _ if name.ends_with(".7z") => Some(Box::new(MemorySource::open_7z(path)?)),
+            #[cfg(feature = "tar")]
+            _ if [".tar", ".tar.gz", ".tgz", ".tar.xz", ".txz"]
+                .iter()
+                .any(|ext| name.ends_with(ext)) =>
+            {
+                Some(Box::new(MemorySource::open_tar(path)?))
+            }
+            _ => None,
+        };
+        let source = source.ok_or_else(|| {
+            Error::Io(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "unsupported archive format",
+            ))
+            .with_file(path)
+        })?;
+        Self::new(source).map_err(|e| e.with_file(path))
+    }
+}
+impl<S> FomodPackage<S> {
+    /// The wrapper folder of the `fomod` directory, e.g. `"MyMod/"`, or `""`.
+    pub fn root(&self) -> &str {
+        &self.root
+    }
+    pub fn info(&self) -> Option<&Info> {
+        self.info.as_ref()
+    }
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+    pub fn source(&self) -> &S {
+        &self.source
+    }
+    /// How paths of the config are normalized when installing.
+    pub fn paths(&self) -> &PathNormalizer {
+        &self.paths
+    }
+    pub fn with_paths(mut self, paths: PathNormalizer) -> Self {
+        self.paths = paths;
+        self
+    }
+    pub fn into_source(self) -> S {
+        self.source
+    }
+
+    /// Whether the package has a file at a path relative to the root.
+    pub fn contains(&self, path: &str) -> bool {
+        key(path).is_some_and(|key| self.files.contains_key(&key))
+    }
+
+    /// Whether the package has any file below a folder relative to the root.
+    pub fn contains_folder(&self, path: &str) -> bool {
+        key(path).is_some_and(|key| key.is_empty()) || !self.folder_entries(path).is_empty()
+    }
+
+    /// The files below a folder relative to the root, as paths relative to
+    /// that folder.
+    pub fn folder_entries(&self, path: &str) -> Vec<&str> {
+        let Some(folder) = key(path) else {
+            return Vec::new();
+        };
+        let (prefix, depth) = match folder.as_str() {
+            "" => (String::new(), 0),
+            folder => (format!("{folder}/"), folder.split('/').count()),
+        };
+
+        self.files
+            .range(prefix.clone()..)
+            .take_while(|(key, _)| key.starts_with(&prefix))
+            .filter_map(|(_, entry)| {
+                let entry = &entry[self.root.len()..];
+                let start = match depth {
+                    0 => 0,
+                    depth => entry.match_indices('/').nth(depth - 1)?.0 + 1,
+                };
+                Some(&entry[start..])
+            })
+            .collect()
+    }
+
+    /// The path in the source of a file relative to the root, found
+    /// case-insensitively.
+    pub fn resolve(&self, path: &str) -> Option<&str> {
+        self.files.get(&key(path)?).map(String::as_str)
+    }
+
+    /// The sources of files and folders, and the paths of images, which are
+    /// named in the config but not part of the package.
+    pub fn missing_sources(&self) -> Vec<&str> {
+        let config = &self.config;
+        let mut files = config.required_install_files.iter().collect::<Vec<_>>();
+        let mut images = config
+            .module_image
+            .iter()
+            .filter_map(|image| image.path.as_deref())
+            .collect::<Vec<_>>();
+        for step in config.install_steps.list() {
+            for group in step.optional_file_groups.list() {
+                for plugin in group.plugins.inner().list() {
+                    files.extend(&plugin.files);
+                    images.extend(plugin.image.as_deref());
+                }
+            }
+        }
+        files.extend(
+            config
+                .conditional_file_installs
+                .iter()
+                .flat_map(|cip| &cip.files),
+        );
+
+        let mut missing = files
+            .into_iter()
+            .filter_map(|file| match file {
+                FileTypeEnum::File(ft) => (!self.contains(&ft.source)).then_some(&ft.source),
+                FileTypeEnum::Folder(ft) => {
+                    (!self.contains_folder(&ft.source)).then_some(&ft.source)
+                }
+            })
+            .map(String::as_str)
+            .chain(images.into_iter().filter(|image| !self.contains(image)))
+            .collect::<Vec<_>>();
+        missing.sort_by_key(|path| key(path));
+        missing.dedup_by_key(|path| key(path));
+        missing
+    }
+
+    /// The files of the package besides `info.xml` and `ModuleConfig.xml`, as
+    /// paths relative to the root.
+    pub fn entries(&self) -> Vec<&str> {
+        self.files
+            .iter()
+            .filter(|(key, _)| *key != INFO && *key != MODULE_CONFIG)
+            .map(|(_, entry)| &entry[self.root.len()..])
+            .collect()
+    }
+}
+
+const INFO: &str = "fomod/info.xml";
+const MODULE_CONFIG: &str = "fomod/moduleconfig.xml";
+
+/// The shallowest folder containing `fomod/ModuleConfig.xml`, with a trailing `/`.
+fn find_root(entries: &[String]) -> Option<String> {
+    entries
+        .iter()
+        .filter(|entry| entry.to_lowercase().ends_with(MODULE_CONFIG))
+        .map(|entry| &entry[..entry.len() - MODULE_CONFIG.len()])
+        .filter(|root| root.is_empty() || root.ends_with('/'))
+        .min_by_key(|root| root.matches('/').count())
+        .map(str::to_string)
+}
+
+/// Paths which leave the root are never part of the package.
+fn key(path: &str) -> Option<String> {
+    normalize(path).ok().map(|path| path.to_lowercase())
+}
+
+#[cfg(all(test, feature = "zip"))]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use super::{FomodPackage, PackageSource, ZipSource};
+
+    const CONFIG: &str = r#"<config><moduleName>Example Mod</moduleName></config>"#;
+    const INFO: &str = r#"<fomod><Name>Example</Name></fomod>"#;
+
+    fn zip(files: &[(&str, &str)]) -> Cursor<Vec<u8>> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, contents) in files {
+            writer.start_file(*name, Default::default()).unwrap();
+            writer.write_all(contents.as_bytes()).unwrap();
+        }
+        let mut cursor = writer.finish().unwrap();
+        cursor.set_position(0);
+        cursor
+    }
+
+    #[test]
+    pub fn archive_files() {
+        let archive = zip(&[("a.txt", "lower"), ("A.txt", "upper"), ("Sub\\b.txt", "b")]);
+        let mut source = ZipSource::new(archive).unwrap();
+        assert_eq!(source.entries(), vec!["a.txt", "A.txt", "Sub/b.txt"]);
+        assert_eq!(source.read("a.txt").unwrap(), b"upper");
+        assert_eq!(source.read("sub/B.TXT").unwrap(), b"b");
+        assert!(source.read("b.txt").is_err());
+
+        assert_eq!(super::read_limited(&b"1234"[..], 4).unwrap(), b"1234");
+        assert!(super::read_limited(&b"12345"[..], 4).is_err());
+    }
+
+    #[test]
+    pub fn zip_packages() {
+        let archive = zip(&[
+            ("Example Mod/FOMOD/Info.xml", INFO),
+            ("Example Mod/FOMOD/moduleconfig.xml", CONFIG),
+            ("Example Mod/FOMOD/images/a.png", ""),
+            ("Example Mod/Data/a.esp", "esp"),
+            ("readme.txt", ""),
+        ]);
+        let mut package = FomodPackage::new(ZipSource::new(archive).unwrap()).unwrap();
+
+        assert_eq!(package.root(), "Example Mod/");
+        assert_eq!(package.config().module_name, "Example Mod");
+        assert_eq!(package.info().unwrap().name.as_deref(), Some("Example"));
+        assert_eq!(package.entries(), vec!["Data/a.esp", "FOMOD/images/a.png"]);
+        assert!(package.contains("data\\A.ESP"));
+        assert_eq!(package.read("data/a.esp").unwrap(), b"esp");
+        assert!(package.read("readme.txt").is_err());
+    }
+
+    #[test]
+    pub fn missing_fomod() {
+        let archive = zip(&[("fomod/info.xml", INFO), ("a.esp", "")]);
+        assert!(FomodPackage::new(ZipSource::new(archive).unwrap()).is_err());
+
+        let archive = zip(&[("fomod/ModuleConfig.xml", "<config>")]);
+        let err = FomodPackage::new(ZipSource::new(archive).unwrap())
+            .err()
+            .unwrap();
+        assert!(err.to_string().starts_with("fomod/ModuleConfig.xml: "));
+    }
+}
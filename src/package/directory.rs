@@ -0,0 +1,184 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{Error, FomodPackage, PackageSource};
+
+/// An unpacked mod in a directory.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DirSource {
+    root: PathBuf,
+    files: Vec<String>,
+}
+impl DirSource {
+    /// Lists the files under `root`; symbolic links are skipped, so that
+    /// nothing outside of it is part of the package.
+    pub fn open(root: impl AsRef<Path>) -> Result<Self, Error> {
+        let root = root.as_ref();
+        let mut files = Vec::new();
+        let mut dirs = vec![(root.to_path_buf(), String::new())];
+
+        while let Some((dir, prefix)) = dirs.pop() {
+            for entry in fs::read_dir(&dir).map_err(|e| Error::from(e).with_file(&dir))? {
+                let entry = entry.map_err(|e| Error::from(e).with_file(&dir))?;
+                let file_type = entry
+                    .file_type()
+                    .map_err(|e| Error::from(e).with_file(entry.path()))?;
+                let path = format!("{prefix}{}", entry.file_name().to_string_lossy());
+                if file_type.is_symlink() {
+                    continue;
+                } else if file_type.is_dir() {
+                    dirs.push((entry.path(), format!("{path}/")));
+                } else {
+                    files.push(path);
+                }
+            }
+        }
+        files.sort();
+
+        Ok(Self {
+            root: root.to_path_buf(),
+            files,
+        })
+    }
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+    /// The path on disk of an entry.
+    pub fn path(&self, entry: &str) -> PathBuf {
+        self.root.join(entry)
+    }
+}
+impl PackageSource for DirSource {
+    fn entries(&self) -> Vec<String> {
+        self.files.clone()
+    }
+    fn read(&mut self, path: &str) -> Result<Vec<u8>, Error> {
+        let file = self.path(path);
+        // Only listed files, so that nothing outside of the root is read.
+        if self
+            .files
+            .binary_search_by(|f| f.as_str().cmp(path))
+            .is_err()
+        {
+            let error = io::Error::new(io::ErrorKind::NotFound, "file not found");
+            return Err(Error::from(error).with_file(file));
+        }
+        fs::read(&file).map_err(|e| Error::from(e).with_file(file))
+    }
+}
+
+impl FomodPackage<DirSource> {
+    /// Opens an unpacked mod.
+    pub fn open_dir(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        Self::new(DirSource::open(path)?).map_err(|e| e.with_file(path))
+    }
+
+    /// The path on disk of a file of the package, found case-insensitively.
+    pub fn path(&self, path: &str) -> Option<PathBuf> {
+        self.resolve(path).map(|entry| self.source().path(entry))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::FomodPackage;
+
+    const CONFIG: &str = r#"
+        <config>
+            <moduleName>Example Mod</moduleName>
+            <moduleImage path="fomod\header.png"/>
+            <requiredInstallFiles>
+                <file source="Core.esp"/>
+                <folder source="textures\"/>
+                <folder source="meshes"/>
+            </requiredInstallFiles>
+            <installSteps order="Explicit">
+                <installStep name="Options">
+                    <optionalFileGroups order="Explicit">
+                        <group name="Options" type="SelectAny">
+                            <plugins order="Explicit">
+                                <plugin name="A">
+                                    <description/>
+                                    <image path="fomod/a.png"/>
+                                    <files><file source="a.esp"/></files>
+                                </plugin>
+                            </plugins>
+                        </group>
+                    </optionalFileGroups>
+                </installStep>
+            </installSteps>
+        </config>
+        "#;
+
+    #[test]
+    pub fn directories() {
+        let root = std::env::temp_dir().join(format!("fomod-dir-{}", std::process::id()));
+        let files = [
+            ("Mod/Fomod/ModuleConfig.xml", CONFIG),
+            ("Mod/fomod/Header.png", ""),
+            ("Mod/core.esp", "esp"),
+            ("Mod/Textures/a.dds", ""),
+        ];
+        for (path, contents) in files {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+
+        let mut package = FomodPackage::open(&root).unwrap();
+        let read = package.read("CORE.ESP");
+        let dir_package = FomodPackage::open_dir(root.join("Mod"));
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(package.root(), "Mod/");
+        assert_eq!(read.unwrap(), b"esp");
+        assert_eq!(
+            package.missing_sources(),
+            vec!["a.esp", "fomod/a.png", "meshes"]
+        );
+
+        let dir_package = dir_package.unwrap();
+        assert_eq!(dir_package.root(), "");
+        assert_eq!(
+            dir_package.path("textures/A.dds"),
+            Some(root.join("Mod").join("Textures/a.dds"))
+        );
+        assert_eq!(dir_package.path("textures/b.dds"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    pub fn symlinks() {
+        use crate::PackageSource;
+        use std::os::unix::fs::symlink;
+
+        let root = std::env::temp_dir().join(format!("fomod-links-{}", std::process::id()));
+        let outside = root.join("outside");
+        fs::create_dir_all(root.join("Mod/fomod")).unwrap();
+        fs::create_dir_all(&outside).unwrap();
+        fs::write(root.join("Mod/fomod/ModuleConfig.xml"), CONFIG).unwrap();
+        fs::write(outside.join("secret.txt"), "secret").unwrap();
+        symlink(root.join("Mod"), root.join("Mod/loop")).unwrap();
+        symlink(&outside, root.join("Mod/textures")).unwrap();
+        symlink(outside.join("secret.txt"), root.join("Mod/core.esp")).unwrap();
+
+        let package = FomodPackage::open_dir(root.join("Mod"));
+        let mut source = package.unwrap().into_source();
+        let secret = outside.join("secret.txt");
+        let reads = [
+            source.read("core.esp"),
+            source.read("textures/secret.txt"),
+            source.read("../outside/secret.txt"),
+            source.read(secret.to_str().unwrap()),
+        ];
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(source.entries(), vec!["fomod/ModuleConfig.xml"]);
+        assert!(reads.iter().all(Result::is_err));
+    }
+}
@@ -0,0 +1,35 @@
+use std::{collections::BTreeMap, io};
+
+use crate::{Error, PackageSource};
+
+/// Files held in memory, e.g. unpacked from an archive which cannot be read
+/// at random.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemorySource {
+    files: BTreeMap<String, Vec<u8>>,
+}
+impl MemorySource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn with_file(mut self, path: &str, bytes: impl Into<Vec<u8>>) -> Self {
+        self.insert(path, bytes);
+        self
+    }
+    pub fn insert(&mut self, path: &str, bytes: impl Into<Vec<u8>>) {
+        let path = path.replace('\\', "/");
+        let path = path.trim_start_matches("./").trim_start_matches('/');
+        self.files.insert(path.to_string(), bytes.into());
+    }
+}
+impl PackageSource for MemorySource {
+    fn entries(&self) -> Vec<String> {
+        self.files.keys().cloned().collect()
+    }
+    fn read(&mut self, path: &str) -> Result<Vec<u8>, Error> {
+        self.files
+            .get(path)
+            .cloned()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "file not found").into())
+    }
+}
@@ -0,0 +1,102 @@
+use std::{
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use sevenz_rust::{Password, SevenZReader};
+
+use crate::{
+    package::{UnpackLimit, MAX_UNPACKED_SIZE},
+    Error, MemorySource,
+};
+
+impl MemorySource {
+    /// Unpacks a .7z archive.
+    pub fn from_7z<R: Read + Seek>(reader: R) -> Result<Self, Error> {
+        Self::unpack_7z(reader, UnpackLimit::new(MAX_UNPACKED_SIZE))
+    }
+
+    fn unpack_7z<R: Read + Seek>(mut reader: R, mut limit: UnpackLimit) -> Result<Self, Error> {
+        let len = reader.seek(SeekFrom::End(0))?;
+        reader.rewind()?;
+
+        let mut archive = SevenZReader::new(reader, len, Password::empty()).map_err(io_error)?;
+        let mut source = Self::new();
+        archive
+            .for_each_entries(|entry, data| {
+                if !entry.is_directory() {
+                    let bytes = limit.read(data)?;
+                    source.insert(entry.name(), bytes);
+                }
+                Ok(true)
+            })
+            .map_err(io_error)?;
+        Ok(source)
+    }
+
+    pub fn open_7z(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        File::open(path)
+            .map_err(Error::from)
+            .and_then(Self::from_7z)
+            .map_err(|e| e.with_file(path))
+    }
+}
+
+fn io_error(e: sevenz_rust::Error) -> Error {
+    match e {
+        sevenz_rust::Error::Io(e, _) | sevenz_rust::Error::FileOpen(e, _) => Error::Io(e),
+        e => Error::Io(io::Error::new(io::ErrorKind::InvalidData, e.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use sevenz_rust::{SevenZArchiveEntry, SevenZWriter};
+
+    use crate::{package::UnpackLimit, FomodPackage, MemorySource};
+
+    fn sevenz(files: &[(&str, &str)]) -> Cursor<Vec<u8>> {
+        let mut writer = SevenZWriter::new(Cursor::new(Vec::new())).unwrap();
+        for (name, contents) in files {
+            let mut entry = SevenZArchiveEntry::new();
+            entry.name = name.to_string();
+            entry.has_stream = true;
+            writer
+                .push_archive_entry(entry, Some(contents.as_bytes()))
+                .unwrap();
+        }
+        let mut cursor = writer.finish().unwrap();
+        cursor.set_position(0);
+        cursor
+    }
+
+    #[test]
+    pub fn sevenz_packages() {
+        let archive = sevenz(&[
+            (
+                "Example/fomod/ModuleConfig.xml",
+                "<config><moduleName>7z</moduleName></config>",
+            ),
+            ("Example/textures/a.dds", "dds"),
+        ]);
+        let mut package = FomodPackage::new(MemorySource::from_7z(archive).unwrap()).unwrap();
+
+        assert_eq!(package.config().module_name, "7z");
+        assert_eq!(package.entries(), vec!["textures/a.dds"]);
+        assert_eq!(package.read("Textures/A.dds").unwrap(), b"dds");
+
+        assert!(MemorySource::from_7z(Cursor::new(b"not a 7z".to_vec())).is_err());
+    }
+
+    #[test]
+    pub fn unpack_limit() {
+        let files = [("a.esp", "esp"), ("b.esp", "esp")];
+
+        assert!(MemorySource::unpack_7z(sevenz(&files), UnpackLimit::new(6)).is_ok());
+        assert!(MemorySource::unpack_7z(sevenz(&files), UnpackLimit::new(5)).is_err());
+    }
+}
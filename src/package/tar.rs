@@ -0,0 +1,149 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Read},
+    path::Path,
+};
+
+use crate::{
+    package::{UnpackLimit, MAX_UNPACKED_SIZE},
+    Error, MemorySource,
+};
+
+impl MemorySource {
+    /// Unpacks a .tar archive.
+    pub fn from_tar<R: Read>(reader: R) -> Result<Self, Error> {
+        Self::unpack_tar(reader, UnpackLimit::new(MAX_UNPACKED_SIZE))
+    }
+
+    fn unpack_tar<R: Read>(reader: R, mut limit: UnpackLimit) -> Result<Self, Error> {
+        let mut archive = tar::Archive::new(reader);
+        let mut source = Self::new();
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            if entry.header().entry_type().is_file() {
+                let path = entry.path()?.to_string_lossy().into_owned();
+                let bytes = limit.read(&mut entry)?;
+                source.insert(&path, bytes);
+            }
+        }
+        Ok(source)
+    }
+
+    /// Unpacks a .tar.gz archive.
+    #[cfg(feature = "tar-gz")]
+    pub fn from_tar_gz<R: Read>(reader: R) -> Result<Self, Error> {
+        Self::from_tar(flate2::read::GzDecoder::new(reader))
+    }
+
+    /// Unpacks a .tar.xz archive.
+    #[cfg(feature = "tar-xz")]
+    pub fn from_tar_xz<R: Read>(reader: R) -> Result<Self, Error> {
+        Self::from_tar(xz2::read::XzDecoder::new(reader))
+    }
+
+    /// Opens a .tar archive, or a compressed one of the enabled formats.
+    pub fn open_tar(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let mut reader = File::open(path)
+            .map(BufReader::new)
+            .map_err(|e| Error::from(e).with_file(path))?;
+        let magic = reader
+            .fill_buf()
+            .map(|bytes| bytes[..bytes.len().min(6)].to_vec())
+            .map_err(|e| Error::from(e).with_file(path))?;
+
+        match magic.as_slice() {
+            #[cfg(feature = "tar-gz")]
+            [0x1F, 0x8B, ..] => Self::from_tar_gz(reader),
+            #[cfg(feature = "tar-xz")]
+            [0xFD, b'7', b'z', b'X', b'Z', 0x00] => Self::from_tar_xz(reader),
+            _ => Self::from_tar(reader),
+        }
+        .map_err(|e| e.with_file(path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{package::UnpackLimit, FomodPackage, MemorySource};
+
+    const CONFIG: &str = "<config><moduleName>tar</moduleName></config>";
+
+    fn tar(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (name, contents) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, name, contents.as_bytes())
+                .unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    #[test]
+    pub fn tar_packages() {
+        let archive = tar(&[("./Fomod/ModuleConfig.xml", CONFIG), ("./a.esp", "esp")]);
+        let mut package = FomodPackage::new(MemorySource::from_tar(&archive[..]).unwrap()).unwrap();
+
+        assert_eq!(package.root(), "");
+        assert_eq!(package.entries(), vec!["a.esp"]);
+        assert_eq!(package.read("A.ESP").unwrap(), b"esp");
+    }
+
+    #[test]
+    pub fn untrusted_sizes() {
+        // The header claims a terabyte, which is neither allocated nor read.
+        let mut header = tar::Header::new_gnu();
+        header.set_path("a.esp").unwrap();
+        header.set_size(1 << 40);
+        header.set_mode(0o644);
+        header.set_cksum();
+        let mut archive = header.as_bytes().to_vec();
+        archive.extend([0; 1024]);
+
+        assert!(MemorySource::from_tar(&archive[..]).is_err());
+    }
+
+    #[test]
+    pub fn unpack_limit() {
+        let archive = tar(&[("a.esp", "esp"), ("b.esp", "esp")]);
+
+        assert!(MemorySource::unpack_tar(&archive[..], UnpackLimit::new(6)).is_ok());
+        assert!(MemorySource::unpack_tar(&archive[..], UnpackLimit::new(5)).is_err());
+    }
+
+    #[cfg(feature = "tar-gz")]
+    #[test]
+    pub fn tar_gz_packages() {
+        use std::io::Write;
+
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), Default::default());
+        encoder
+            .write_all(&tar(&[("mod/fomod/ModuleConfig.xml", CONFIG)]))
+            .unwrap();
+        let path = std::env::temp_dir().join(format!("fomod-{}.tar.gz", std::process::id()));
+        std::fs::write(&path, encoder.finish().unwrap()).unwrap();
+
+        let package = FomodPackage::open(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(package.unwrap().root(), "mod/");
+    }
+
+    #[cfg(feature = "tar-xz")]
+    #[test]
+    pub fn tar_xz_packages() {
+        use std::io::Write;
+
+        let mut encoder = xz2::write::XzEncoder::new(Vec::new(), 6);
+        encoder
+            .write_all(&tar(&[("fomod/ModuleConfig.xml", CONFIG)]))
+            .unwrap();
+        let archive = encoder.finish().unwrap();
+
+        let package = FomodPackage::new(MemorySource::from_tar_xz(&archive[..]).unwrap());
+        assert_eq!(package.unwrap().config().module_name, "tar");
+    }
+}
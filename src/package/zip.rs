@@ -0,0 +1,76 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, Read, Seek},
+    path::Path,
+};
+
+use zip::ZipArchive;
+
+use crate::{
+    package::{key, read_limited, MAX_FILE_SIZE},
+    Error, FomodPackage, PackageSource,
+};
+
+/// A .zip archive.
+pub struct ZipSource<R> {
+    archive: ZipArchive<R>,
+    /// The names of the files in the archive.
+    names: Vec<String>,
+    /// Indices in the archive, by normalized lowercase name; of names
+    /// differing only in case the last is found, as in `FomodPackage`.
+    index: HashMap<String, usize>,
+}
+impl ZipSource<File> {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        File::open(path)
+            .map_err(Error::from)
+            .and_then(Self::new)
+            .map_err(|e| e.with_file(path))
+    }
+}
+impl<R: Read + Seek> ZipSource<R> {
+    pub fn new(reader: R) -> Result<Self, Error> {
+        let mut archive = ZipArchive::new(reader).map_err(io::Error::from)?;
+        let mut names = Vec::new();
+        let mut index = HashMap::new();
+        for idx in 0..archive.len() {
+            let file = archive.by_index_raw(idx).map_err(io::Error::from)?;
+            if file.is_file() {
+                let name = file.name().replace('\\', "/");
+                if let Some(key) = key(&name) {
+                    index.insert(key, idx);
+                }
+                names.push(name);
+            }
+        }
+        Ok(Self {
+            archive,
+            names,
+            index,
+        })
+    }
+}
+impl<R: Read + Seek> PackageSource for ZipSource<R> {
+    fn entries(&self) -> Vec<String> {
+        self.names.clone()
+    }
+    fn read(&mut self, path: &str) -> Result<Vec<u8>, Error> {
+        let idx = key(path)
+            .and_then(|key| self.index.get(&key))
+            .copied()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "file not found"))?;
+
+        let file = self.archive.by_index(idx).map_err(io::Error::from)?;
+        Ok(read_limited(file, MAX_FILE_SIZE)?)
+    }
+}
+
+impl FomodPackage<ZipSource<File>> {
+    /// Opens a .zip archive.
+    pub fn open_zip(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        Self::new(ZipSource::open(path)?).map_err(|e| e.with_file(path))
+    }
+}
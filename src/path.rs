@@ -0,0 +1,115 @@
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PathError {
+    /// A path with a root, drive or prefix, e.g. `C:\` or `/`.
+    Absolute(String),
+    /// A path which leaves its root through `..`.
+    Escaping(String),
+}
+impl fmt::Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Absolute(path) => write!(f, "path '{path}' is absolute"),
+            Self::Escaping(path) => write!(f, "path '{path}' leaves the installation root"),
+        }
+    }
+}
+impl std::error::Error for PathError {}
+
+/// Turns the paths of a fomod into relative paths with `/` as separator.
+///
+/// The paths are untrusted, so anything which could point outside of the
+/// package or the installation root is rejected.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct PathNormalizer {
+    stripped_roots: Vec<String>,
+}
+impl PathNormalizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Strips a leading folder from destinations, e.g. `Data` for mods which
+    /// are installed into the `Data` folder already.
+    pub fn with_stripped_root(mut self, root: &str) -> Self {
+        self.stripped_roots
+            .push(root.trim_matches(['/', '\\']).to_lowercase());
+        self
+    }
+
+    /// Normalizes a path into the package.
+    pub fn source(&self, path: &str) -> Result<String, PathError> {
+        normalize(path)
+    }
+
+    /// Normalizes a path into the installation, stripping configured roots.
+    pub fn destination(&self, path: &str) -> Result<String, PathError> {
+        let normalized = normalize(path)?;
+        let stripped = self.stripped_roots.iter().find_map(|root| {
+            let (first, rest) = normalized.split_once('/').unwrap_or((&normalized, ""));
+            first.eq_ignore_ascii_case(root).then_some(rest)
+        });
+        Ok(stripped.map(str::to_string).unwrap_or(normalized))
+    }
+}
+
+/// Converts backslashes and resolves `.` and `..`.
+pub fn normalize(path: &str) -> Result<String, PathError> {
+    let converted = path.replace('\\', "/");
+    if converted.starts_with('/') || converted.contains(':') {
+        return Err(PathError::Absolute(path.to_string()));
+    }
+
+    let mut components = Vec::new();
+    for component in converted.split('/') {
+        match component {
+            "" | "." => {}
+            ".." => {
+                if components.pop().is_none() {
+                    return Err(PathError::Escaping(path.to_string()));
+                }
+            }
+            // Windows ignores trailing dots and spaces, so `.. ` is `..` as well.
+            c if c.trim_end_matches([' ', '.']).is_empty() => {
+                return Err(PathError::Escaping(path.to_string()))
+            }
+            c => components.push(c),
+        }
+    }
+    Ok(components.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{normalize, PathError, PathNormalizer};
+
+    #[test]
+    pub fn normalized_paths() {
+        assert_eq!(normalize(r"textures\a.dds").unwrap(), "textures/a.dds");
+        assert_eq!(normalize(r".\a\\b\..\c/").unwrap(), "a/c");
+        assert_eq!(normalize("").unwrap(), "");
+
+        for path in [r"C:\Windows", "/etc/passwd", r"\\server\share", "c:a"] {
+            assert_eq!(normalize(path), Err(PathError::Absolute(path.to_string())));
+        }
+        for path in [
+            r"..\..\a",
+            "a/../../b",
+            "a/.. /b/../..",
+            "...",
+            "a/. /../..",
+        ] {
+            assert_eq!(normalize(path), Err(PathError::Escaping(path.to_string())));
+        }
+    }
+
+    #[test]
+    pub fn stripped_roots() {
+        let paths = PathNormalizer::new().with_stripped_root(r"Data\");
+        assert_eq!(paths.destination(r"DATA\a.esp").unwrap(), "a.esp");
+        assert_eq!(paths.destination("Data").unwrap(), "");
+        assert_eq!(paths.destination("database/a").unwrap(), "database/a");
+        assert_eq!(paths.source(r"Data\a.esp").unwrap(), "Data/a.esp");
+        assert!(paths.destination(r"Data\..\..\a").is_err());
+    }
+}
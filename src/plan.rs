@@ -0,0 +1,389 @@
+use crate::{
+    path::{PathError, PathNormalizer},
+    Config, FileTypeEnum, Flags, GameEnvironment, Group, InstallStep, Plugin, PluginTypeEnum,
+};
+
+/// What caused a file to be part of an `InstallPlan`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FileOrigin {
+    Required,
+    Plugin {
+        step: String,
+        group: String,
+        plugin: String,
+    },
+    /// Index into `Config::conditional_file_installs`.
+    Conditional(usize),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PlannedFile {
+    pub file: FileTypeEnum,
+    pub origin: FileOrigin,
+}
+impl PlannedFile {
+    pub fn is_folder(&self) -> bool {
+        matches!(self.file, FileTypeEnum::Folder(_))
+    }
+    pub fn source(&self) -> &str {
+        match &self.file {
+            FileTypeEnum::File(ft) | FileTypeEnum::Folder(ft) => &ft.source,
+        }
+    }
+    /// Where the file or folder ends up, relative to the installation root.
+    ///
+    /// A folder without destination is merged into the root, a file without
+    /// destination keeps its source path, and a file with an empty
+    /// destination is placed in the root.
+    pub fn destination(&self) -> Result<String, PathError> {
+        self.destination_with(&PathNormalizer::default())
+    }
+    /// Like `destination`, normalized with the given rules.
+    pub fn destination_with(&self, paths: &PathNormalizer) -> Result<String, PathError> {
+        let destination = match &self.file {
+            FileTypeEnum::Folder(ft) => ft.destination.clone().unwrap_or_default(),
+            FileTypeEnum::File(ft) => match ft.destination.as_deref() {
+                None => ft.source.clone(),
+                Some("") => ft
+                    .source
+                    .rsplit(['/', '\\'])
+                    .next()
+                    .unwrap_or_default()
+                    .to_string(),
+                Some(d) => d.to_string(),
+            },
+        };
+        paths.destination(&destination)
+    }
+    pub fn priority(&self) -> isize {
+        match &self.file {
+            FileTypeEnum::File(ft) | FileTypeEnum::Folder(ft) => ft.priority.unwrap_or_default(),
+        }
+    }
+}
+
+/// A plugin as presented to the user, and whether it was selected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PluginChoice<'a> {
+    pub step: &'a InstallStep,
+    pub group: &'a Group,
+    pub plugin: &'a Plugin,
+    /// The type of the plugin with the flags its step was presented with.
+    pub plugin_type: PluginTypeEnum,
+    pub selected: bool,
+}
+
+/// The file operations of an installation.
+///
+/// Files are ordered by ascending priority and should be installed in order,
+/// so that a later entry overwrites an earlier one with the same destination.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct InstallPlan {
+    pub files: Vec<PlannedFile>,
+}
+impl InstallPlan {
+    /// The files which are not overwritten by a later file with the same destination.
+    ///
+    /// Folders are always kept, as their contents are not known to the plan.
+    pub fn effective_files(&self) -> Vec<&PlannedFile> {
+        let key = |pf: &PlannedFile| pf.destination().map(|d| d.to_lowercase());
+
+        self.files
+            .iter()
+            .enumerate()
+            .filter(|(idx, pf)| {
+                pf.is_folder()
+                    || !self.files[idx + 1..]
+                        .iter()
+                        .any(|later| !later.is_folder() && key(later) == key(pf))
+            })
+            .map(|(_, pf)| pf)
+            .collect()
+    }
+
+    /// Checks that every source and destination is a relative path which stays
+    /// inside its root.
+    pub fn validate_paths(&self, paths: &PathNormalizer) -> Result<(), PathError> {
+        for pf in &self.files {
+            paths.source(pf.source())?;
+            pf.destination_with(paths)?;
+        }
+        Ok(())
+    }
+}
+
+impl Config {
+    /// Computes the files to install for the given plugin choices.
+    ///
+    /// `flags` are the flags after the last step and decide which conditional
+    /// file installs apply. Files of unselected plugins are still installed when
+    /// marked `alwaysInstall`, or `installIfUsable` when the plugin was usable
+    /// at its step.
+    pub fn install_plan<'a, E, I>(&self, choices: I, env: &E, flags: &Flags) -> InstallPlan
+    where
+        E: GameEnvironment + ?Sized,
+        I: IntoIterator<Item = PluginChoice<'a>>,
+    {
+        let mut files = Vec::new();
+
+        files.extend(self.required_install_files.iter().map(|f| PlannedFile {
+            file: f.clone(),
+            origin: FileOrigin::Required,
+        }));
+
+        for choice in choices {
+            let usable = choice.plugin_type != PluginTypeEnum::NotUsable;
+            let origin = FileOrigin::Plugin {
+                step: choice.step.name.clone(),
+                group: choice.group.name.clone(),
+                plugin: choice.plugin.name.clone(),
+            };
+
+            files.extend(
+                choice
+                    .plugin
+                    .files
+                    .iter()
+                    .filter(|f| {
+                        let ft = match f {
+                            FileTypeEnum::File(ft) | FileTypeEnum::Folder(ft) => ft,
+                        };
+                        choice.selected
+                            || ft
+                                .always_install
+                                .as_deref()
+                                .map(|ai| ai.eq_ignore_ascii_case("true") || ai == "1")
+                                .unwrap_or(false)
+                            || (ft.install_if_usable && usable)
+                    })
+                    .map(|f| PlannedFile {
+                        file: f.clone(),
+                        origin: origin.clone(),
+                    }),
+            );
+        }
+
+        for (idx, cip) in self.conditional_file_installs.iter().enumerate() {
+            if cip.dependencies.evaluate(env, flags) {
+                files.extend(cip.files.iter().map(|f| PlannedFile {
+                    file: f.clone(),
+                    origin: FileOrigin::Conditional(idx),
+                }));
+            }
+        }
+
+        files.sort_by_key(PlannedFile::priority);
+        InstallPlan { files }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FileOrigin, PluginChoice};
+    use crate::{Config, Flags, InstallerSession, MemoryEnvironment, PluginTypeEnum};
+
+    const XML: &str = r#"
+        <config xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance"
+            xsi:noNamespaceSchemaLocation="http://qconsulting.ca/fo3/ModConfig5.0.xsd">
+
+            <moduleName>Example Mod</moduleName>
+
+            <requiredInstallFiles>
+                <file source="core.esp" destination=""/>
+                <folder source="core" priority="-1"/>
+            </requiredInstallFiles>
+
+            <installSteps order="Explicit">
+                <installStep name="Choose Option">
+                    <optionalFileGroups order="Explicit">
+                        <group name="Select an option:" type="SelectExactlyOne">
+                            <plugins order="Explicit">
+                                <plugin name="Option A">
+                                    <description>Option A</description>
+                                    <files>
+                                        <file source="a/textures/blue.dds" destination="textures/blue.dds"/>
+                                        <file source="readme_a.txt" alwaysInstall="true"/>
+                                    </files>
+                                    <conditionFlags>
+                                        <flag name="option">a</flag>
+                                    </conditionFlags>
+                                </plugin>
+                                <plugin name="Option B">
+                                    <description>Option B</description>
+                                    <files>
+                                        <file source="b/textures/blue.dds" destination="textures/blue.dds"/>
+                                        <file source="readme_b.txt" installIfUsable="true"/>
+                                    </files>
+                                    <conditionFlags>
+                                        <flag name="option">b</flag>
+                                    </conditionFlags>
+                                </plugin>
+                            </plugins>
+                        </group>
+                    </optionalFileGroups>
+                </installStep>
+            </installSteps>
+
+            <conditionalFileInstalls>
+                <patterns>
+                    <pattern>
+                        <dependencies operator="And">
+                            <flagDependency flag="option" value="a"/>
+                        </dependencies>
+                        <files>
+                            <file source="a/textures/blue_hd.dds" destination="textures/blue.dds" priority="1"/>
+                        </files>
+                    </pattern>
+                    <pattern>
+                        <dependencies operator="And">
+                            <flagDependency flag="option" value="b"/>
+                        </dependencies>
+                        <files>
+                            <folder source="b/meshes" destination="meshes"/>
+                        </files>
+                    </pattern>
+                </patterns>
+            </conditionalFileInstalls>
+        </config>
+        "#;
+
+    #[test]
+    pub fn session_plan() {
+        let config = Config::try_from(XML).unwrap();
+        let env = MemoryEnvironment::new();
+        let mut session = InstallerSession::new(&config, &env);
+        assert!(session.next().unwrap().is_none());
+
+        let plan = session.install_plan();
+        let files = plan
+            .files
+            .iter()
+            .map(|pf| (pf.source(), pf.destination().unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            files,
+            vec![
+                ("core", "".to_string()),
+                ("core.esp", "core.esp".to_string()),
+                ("a/textures/blue.dds", "textures/blue.dds".to_string()),
+                ("readme_a.txt", "readme_a.txt".to_string()),
+                ("readme_b.txt", "readme_b.txt".to_string()),
+                ("a/textures/blue_hd.dds", "textures/blue.dds".to_string()),
+            ]
+        );
+        assert_eq!(plan.files[0].origin, FileOrigin::Required);
+        assert_eq!(plan.files[5].origin, FileOrigin::Conditional(0));
+
+        let effective = plan
+            .effective_files()
+            .iter()
+            .map(|pf| pf.source())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            effective,
+            vec![
+                "core",
+                "core.esp",
+                "readme_a.txt",
+                "readme_b.txt",
+                "a/textures/blue_hd.dds"
+            ]
+        );
+    }
+
+    #[test]
+    pub fn explicit_choices() {
+        let config = Config::try_from(XML).unwrap();
+        let step = &config.install_steps.list()[0];
+        let group = &step.optional_file_groups.list()[0];
+        let plugins = group.plugins.inner().list();
+
+        let mut flags = Flags::new();
+        flags.extend(plugins[1].condition_flags.iter());
+
+        let choices = plugins.iter().map(|plugin| PluginChoice {
+            step,
+            group,
+            plugin,
+            plugin_type: PluginTypeEnum::Optional,
+            selected: plugin.name == "Option B",
+        });
+        let plan = config.install_plan(choices, &MemoryEnvironment::new(), &flags);
+
+        let origins = plan
+            .files
+            .iter()
+            .map(|pf| (pf.source(), &pf.origin))
+            .collect::<Vec<_>>();
+        assert_eq!(origins[3].0, "b/textures/blue.dds");
+        assert_eq!(
+            origins[3].1,
+            &FileOrigin::Plugin {
+                step: "Choose Option".to_string(),
+                group: "Select an option:".to_string(),
+                plugin: "Option B".to_string()
+            }
+        );
+        assert_eq!(origins[5], ("b/meshes", &FileOrigin::Conditional(1)));
+        assert_eq!(plan.files.len(), 6);
+    }
+
+    #[test]
+    pub fn usable_at_step() {
+        let xml = r#"
+        <config>
+            <moduleName>Example Mod</moduleName>
+            <installSteps order="Explicit">
+                <installStep name="Options">
+                    <optionalFileGroups order="Explicit">
+                        <group name="Patches" type="SelectAny">
+                            <plugins order="Explicit">
+                                <plugin name="Patch">
+                                    <description/>
+                                    <files><file source="patch.esp" installIfUsable="true"/></files>
+                                    <typeDescriptor>
+                                        <dependencyType>
+                                            <defaultType name="Optional"/>
+                                            <patterns>
+                                                <pattern>
+                                                    <dependencies>
+                                                        <flagDependency flag="merged" value="On"/>
+                                                    </dependencies>
+                                                    <type name="NotUsable"/>
+                                                </pattern>
+                                            </patterns>
+                                        </dependencyType>
+                                    </typeDescriptor>
+                                </plugin>
+                            </plugins>
+                        </group>
+                    </optionalFileGroups>
+                </installStep>
+                <installStep name="Merge">
+                    <optionalFileGroups order="Explicit">
+                        <group name="Merge" type="SelectAll">
+                            <plugins order="Explicit">
+                                <plugin name="Merged">
+                                    <description/>
+                                    <conditionFlags><flag name="merged">On</flag></conditionFlags>
+                                </plugin>
+                            </plugins>
+                        </group>
+                    </optionalFileGroups>
+                </installStep>
+            </installSteps>
+        </config>
+        "#;
+        let config = Config::try_from(xml).unwrap();
+        let env = MemoryEnvironment::new();
+        let mut session = InstallerSession::new(&config, &env);
+        session.next().unwrap();
+        session.next().unwrap();
+
+        // Merged only makes the patch unusable after it was shown.
+        assert_eq!(session.flags().get("merged"), "On");
+        let plan = session.install_plan();
+        assert_eq!(plan.files.len(), 1);
+        assert_eq!(plan.files[0].source(), "patch.esp");
+    }
+}
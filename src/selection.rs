@@ -0,0 +1,316 @@
+use std::fmt;
+
+use crate::{Flags, GameEnvironment, Group, GroupType, PluginTypeEnum};
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SelectionError {
+    /// A `SelectExactlyOne` group with any other number of selected plugins.
+    ExactlyOne { group: String, selected: usize },
+    /// A `SelectAtMostOne` group with more than one selected plugin.
+    AtMostOne { group: String, selected: usize },
+    /// A `SelectAtLeastOne` group without a selected plugin.
+    AtLeastOne { group: String },
+    /// A `SelectAll` group where some usable plugins are not selected.
+    All { group: String, missing: Vec<String> },
+    /// A plugin of type `Required` which is not selected.
+    RequiredNotSelected { group: String, plugin: String },
+    /// A plugin of type `NotUsable` which is selected.
+    NotUsableSelected { group: String, plugin: String },
+    /// A selected plugin which does not exist in the group.
+    UnknownPlugin { group: String, plugin: String },
+    /// A group which does not exist in the current step.
+    UnknownGroup { group: String },
+}
+impl fmt::Display for SelectionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ExactlyOne { group, selected } => {
+                write!(f, "group '{group}': SelectExactlyOne got {selected}")
+            }
+            Self::AtMostOne { group, selected } => {
+                write!(f, "group '{group}': SelectAtMostOne got {selected}")
+            }
+            Self::AtLeastOne { group } => write!(f, "group '{group}': SelectAtLeastOne got 0"),
+            Self::All { group, missing } => write!(
+                f,
+                "group '{group}': SelectAll is missing {}",
+                missing.join(", ")
+            ),
+            Self::RequiredNotSelected { group, plugin } => {
+                write!(
+                    f,
+                    "group '{group}': required plugin '{plugin}' is not selected"
+                )
+            }
+            Self::NotUsableSelected { group, plugin } => {
+                write!(f, "group '{group}': plugin '{plugin}' is not usable")
+            }
+            Self::UnknownPlugin { group, plugin } => {
+                write!(f, "group '{group}': unknown plugin '{plugin}'")
+            }
+            Self::UnknownGroup { group } => write!(f, "unknown group '{group}'"),
+        }
+    }
+}
+impl std::error::Error for SelectionError {}
+
+impl Group {
+    /// Checks the selected plugins against the group type and the plugin types.
+    ///
+    /// `selected` holds indices into the sorted plugins of this group.
+    pub fn validate_selection<E: GameEnvironment + ?Sized>(
+        &self,
+        selected: &[usize],
+        env: &E,
+        flags: &Flags,
+    ) -> Result<(), SelectionError> {
+        let plugins = self.plugins.inner().sorted();
+
+        let mut selected = selected.to_vec();
+        selected.sort_unstable();
+        selected.dedup();
+
+        if let Some(&idx) = selected.iter().find(|&&idx| idx >= plugins.len()) {
+            return Err(SelectionError::UnknownPlugin {
+                group: self.name.clone(),
+                plugin: format!("#{idx}"),
+            });
+        }
+
+        let types = plugins
+            .iter()
+            .map(|p| p.plugin_type(env, flags))
+            .collect::<Vec<_>>();
+
+        for (idx, plugin) in plugins.iter().enumerate() {
+            let is_selected = selected.contains(&idx);
+            match types[idx] {
+                PluginTypeEnum::NotUsable if is_selected => {
+                    return Err(SelectionError::NotUsableSelected {
+                        group: self.name.clone(),
+                        plugin: plugin.name.clone(),
+                    })
+                }
+                PluginTypeEnum::Required if !is_selected => {
+                    return Err(SelectionError::RequiredNotSelected {
+                        group: self.name.clone(),
+                        plugin: plugin.name.clone(),
+                    })
+                }
+                _ => {}
+            }
+        }
+
+        let count = selected.len();
+        match self.plugins {
+            GroupType::SelectExactlyOne(_) if count != 1 => Err(SelectionError::ExactlyOne {
+                group: self.name.clone(),
+                selected: count,
+            }),
+            GroupType::SelectAtMostOne(_) if count > 1 => Err(SelectionError::AtMostOne {
+                group: self.name.clone(),
+                selected: count,
+            }),
+            GroupType::SelectAtLeastOne(_) if count == 0 => Err(SelectionError::AtLeastOne {
+                group: self.name.clone(),
+            }),
+            GroupType::SelectAll(_) => {
+                let missing = plugins
+                    .iter()
+                    .enumerate()
+                    .filter(|(idx, _)| {
+                        !selected.contains(idx) && types[*idx] != PluginTypeEnum::NotUsable
+                    })
+                    .map(|(_, p)| p.name.clone())
+                    .collect::<Vec<_>>();
+                if missing.is_empty() {
+                    Ok(())
+                } else {
+                    Err(SelectionError::All {
+                        group: self.name.clone(),
+                        missing,
+                    })
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Like `validate_selection`, with the plugins selected by name.
+    pub fn validate_selected_names<E: GameEnvironment + ?Sized>(
+        &self,
+        selected: &[&str],
+        env: &E,
+        flags: &Flags,
+    ) -> Result<(), SelectionError> {
+        let indices = self.plugin_indices(selected)?;
+        self.validate_selection(&indices, env, flags)
+    }
+
+    /// Maps plugin names to indices into the sorted plugins of this group.
+    pub fn plugin_indices(&self, names: &[&str]) -> Result<Vec<usize>, SelectionError> {
+        let plugins = self.plugins.inner().sorted();
+        names
+            .iter()
+            .map(|name| {
+                plugins.iter().position(|p| p.name == *name).ok_or_else(|| {
+                    SelectionError::UnknownPlugin {
+                        group: self.name.clone(),
+                        plugin: name.to_string(),
+                    }
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SelectionError;
+    use crate::{Config, Flags, Group, MemoryEnvironment};
+
+    const XML: &str = r#"
+        <config xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance"
+            xsi:noNamespaceSchemaLocation="http://qconsulting.ca/fo3/ModConfig5.0.xsd">
+
+            <moduleName>Example Mod</moduleName>
+
+            <installSteps order="Explicit">
+                <installStep name="Options">
+                    <optionalFileGroups order="Explicit">
+                        <group name="Exactly" type="SelectExactlyOne">
+                            <plugins order="Explicit">
+                                <plugin name="A"><description/></plugin>
+                                <plugin name="B"><description/></plugin>
+                                <plugin name="Broken">
+                                    <description/>
+                                    <typeDescriptor><type name="NotUsable"/></typeDescriptor>
+                                </plugin>
+                            </plugins>
+                        </group>
+                        <group name="AtMost" type="SelectAtMostOne">
+                            <plugins order="Explicit">
+                                <plugin name="A"><description/></plugin>
+                                <plugin name="B"><description/></plugin>
+                            </plugins>
+                        </group>
+                        <group name="AtLeast" type="SelectAtLeastOne">
+                            <plugins order="Explicit">
+                                <plugin name="A"><description/></plugin>
+                                <plugin name="Core">
+                                    <description/>
+                                    <typeDescriptor><type name="Required"/></typeDescriptor>
+                                </plugin>
+                            </plugins>
+                        </group>
+                        <group name="All" type="SelectAll">
+                            <plugins order="Explicit">
+                                <plugin name="A"><description/></plugin>
+                                <plugin name="B"><description/></plugin>
+                                <plugin name="Broken">
+                                    <description/>
+                                    <typeDescriptor><type name="NotUsable"/></typeDescriptor>
+                                </plugin>
+                            </plugins>
+                        </group>
+                        <group name="Any" type="SelectAny">
+                            <plugins order="Explicit">
+                                <plugin name="A"><description/></plugin>
+                            </plugins>
+                        </group>
+                    </optionalFileGroups>
+                </installStep>
+            </installSteps>
+        </config>
+        "#;
+
+    fn groups() -> Vec<Group> {
+        let config = Config::try_from(XML).unwrap();
+        config.install_steps.list()[0]
+            .optional_file_groups
+            .list()
+            .clone()
+    }
+
+    fn validate(group: &Group, names: &[&str]) -> Result<(), SelectionError> {
+        group.validate_selected_names(names, &MemoryEnvironment::new(), &Flags::new())
+    }
+
+    #[test]
+    pub fn group_types() {
+        let groups = groups();
+
+        assert!(validate(&groups[0], &["A"]).is_ok());
+        assert_eq!(
+            validate(&groups[0], &["A", "B"]).unwrap_err().to_string(),
+            "group 'Exactly': SelectExactlyOne got 2"
+        );
+        assert_eq!(
+            validate(&groups[0], &[]),
+            Err(SelectionError::ExactlyOne {
+                group: "Exactly".to_string(),
+                selected: 0
+            })
+        );
+
+        assert!(validate(&groups[1], &[]).is_ok());
+        assert!(validate(&groups[1], &["B"]).is_ok());
+        assert_eq!(
+            validate(&groups[1], &["A", "B"]),
+            Err(SelectionError::AtMostOne {
+                group: "AtMost".to_string(),
+                selected: 2
+            })
+        );
+
+        assert!(validate(&groups[3], &["A", "B"]).is_ok());
+        assert_eq!(
+            validate(&groups[3], &["A"]),
+            Err(SelectionError::All {
+                group: "All".to_string(),
+                missing: vec!["B".to_string()]
+            })
+        );
+
+        assert!(validate(&groups[4], &[]).is_ok());
+        assert!(validate(&groups[4], &["A"]).is_ok());
+    }
+
+    #[test]
+    pub fn plugin_types() {
+        let groups = groups();
+
+        assert_eq!(
+            validate(&groups[0], &["Broken"]),
+            Err(SelectionError::NotUsableSelected {
+                group: "Exactly".to_string(),
+                plugin: "Broken".to_string()
+            })
+        );
+        assert_eq!(
+            validate(&groups[2], &["A"]),
+            Err(SelectionError::RequiredNotSelected {
+                group: "AtLeast".to_string(),
+                plugin: "Core".to_string()
+            })
+        );
+        assert!(validate(&groups[2], &["Core"]).is_ok());
+    }
+
+    #[test]
+    pub fn unknown_plugins() {
+        let groups = groups();
+        let env = MemoryEnvironment::new();
+
+        assert_eq!(
+            validate(&groups[4], &["B"]),
+            Err(SelectionError::UnknownPlugin {
+                group: "Any".to_string(),
+                plugin: "B".to_string()
+            })
+        );
+        assert!(groups[4]
+            .validate_selection(&[1], &env, &Flags::new())
+            .is_err());
+    }
+}
@@ -0,0 +1,592 @@
+use crate::{
+    Config, Flags, GameEnvironment, Group, GroupType, InstallPlan, InstallStep, Plugin,
+    PluginChoice, PluginTypeEnum, SelectionError,
+};
+
+struct SessionGroup<'a> {
+    group: &'a Group,
+    plugins: Vec<&'a Plugin>,
+}
+
+struct SessionStep<'a> {
+    step: &'a InstallStep,
+    groups: Vec<SessionGroup<'a>>,
+}
+
+/// Walks the install steps of a `Config` the way an installer wizard would.
+///
+/// Steps, groups and plugins are presented in their sorted order and all
+/// indices taken and returned by the session refer to that order. Steps whose
+/// `visible` condition does not hold for the flags set by the previously
+/// visited steps are skipped.
+pub struct InstallerSession<'a, E: GameEnvironment + ?Sized> {
+    config: &'a Config,
+    env: &'a E,
+    steps: Vec<SessionStep<'a>>,
+    selections: Vec<Option<Vec<Vec<usize>>>>,
+    history: Vec<usize>,
+    finished: bool,
+}
+impl<'a, E: GameEnvironment + ?Sized> InstallerSession<'a, E> {
+    /// Creates a session positioned on the first visible step.
+    pub fn new(config: &'a Config, env: &'a E) -> Self {
+        let steps = config
+            .install_steps
+            .sorted()
+            .into_iter()
+            .map(|step| SessionStep {
+                step,
+                groups: step
+                    .optional_file_groups
+                    .sorted()
+                    .into_iter()
+                    .map(|group| SessionGroup {
+                        group,
+                        plugins: group.plugins.inner().sorted(),
+                    })
+                    .collect(),
+            })
+            .collect::<Vec<_>>();
+
+        let mut session = Self {
+            config,
+            env,
+            selections: steps.iter().map(|_| None).collect(),
+            steps,
+            history: Vec::new(),
+            finished: false,
+        };
+        session.advance();
+        session
+    }
+
+    pub fn config(&self) -> &'a Config {
+        self.config
+    }
+
+    /// Whether `Config::module_dependencies` are met by the environment.
+    pub fn module_dependencies_met(&self) -> bool {
+        self.config
+            .module_dependencies
+            .as_ref()
+            .map(|md| md.evaluate(self.env, &Flags::new()))
+            .unwrap_or(true)
+    }
+
+    /// The current step, `None` once the session has moved past the last visible step.
+    pub fn current_step(&self) -> Option<&'a InstallStep> {
+        self.current().map(|idx| self.steps[idx].step)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// The groups of the current step.
+    pub fn groups(&self) -> Vec<&'a Group> {
+        self.current()
+            .map(|idx| self.steps[idx].groups.iter().map(|g| g.group).collect())
+            .unwrap_or_default()
+    }
+
+    /// The plugins of `group` in the current step.
+    pub fn plugins(&self, group: usize) -> Vec<&'a Plugin> {
+        self.current()
+            .and_then(|idx| self.steps[idx].groups.get(group))
+            .map(|g| g.plugins.clone())
+            .unwrap_or_default()
+    }
+
+    /// The indices of the selected plugins of `group` in the current step.
+    pub fn selected(&self, group: usize) -> &[usize] {
+        self.current()
+            .and_then(|idx| self.selections[idx].as_ref())
+            .and_then(|s| s.get(group))
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    pub fn is_selected(&self, group: usize, plugin: usize) -> bool {
+        self.selected(group).contains(&plugin)
+    }
+
+    /// Selects a plugin; in `SelectExactlyOne` and `SelectAtMostOne` groups
+    /// this replaces the previous selection.
+    pub fn select(&mut self, group: usize, plugin: usize) -> Result<(), SelectionError> {
+        let single = matches!(
+            self.group(group)?.plugins,
+            GroupType::SelectExactlyOne(_) | GroupType::SelectAtMostOne(_)
+        );
+        let selection = self.selection_mut(group, &[plugin])?;
+        if single {
+            selection.clear();
+        }
+        if !selection.contains(&plugin) {
+            selection.push(plugin);
+            selection.sort_unstable();
+        }
+        Ok(())
+    }
+
+    pub fn deselect(&mut self, group: usize, plugin: usize) -> Result<(), SelectionError> {
+        self.selection_mut(group, &[plugin])?
+            .retain(|p| *p != plugin);
+        Ok(())
+    }
+
+    /// Replaces the selection of `group` in the current step as is; unlike
+    /// `select`, no plugin is deselected for single choice groups.
+    pub fn set_selected(&mut self, group: usize, plugins: &[usize]) -> Result<(), SelectionError> {
+        let selection = self.selection_mut(group, plugins)?;
+        *selection = plugins.to_vec();
+        selection.sort_unstable();
+        selection.dedup();
+        Ok(())
+    }
+
+    /// A group of the current step.
+    fn group(&self, group: usize) -> Result<&'a Group, SelectionError> {
+        self.groups()
+            .get(group)
+            .copied()
+            .ok_or(SelectionError::UnknownGroup {
+                group: format!("#{group}"),
+            })
+    }
+
+    /// The selection of a group of the current step, once `plugins` are
+    /// known to be in it.
+    fn selection_mut(
+        &mut self,
+        group: usize,
+        plugins: &[usize],
+    ) -> Result<&mut Vec<usize>, SelectionError> {
+        let count = self.plugins(group).len();
+        let name = &self.group(group)?.name;
+        if let Some(plugin) = plugins.iter().find(|&&plugin| plugin >= count) {
+            return Err(SelectionError::UnknownPlugin {
+                group: name.clone(),
+                plugin: format!("#{plugin}"),
+            });
+        }
+        // A visited step always has selections.
+        let idx = self.current().unwrap();
+        Ok(&mut self.selections[idx].as_mut().unwrap()[group])
+    }
+
+    /// The type of a plugin of `group` in the current step.
+    pub fn plugin_type(
+        &self,
+        group: usize,
+        plugin: usize,
+    ) -> Result<PluginTypeEnum, SelectionError> {
+        let name = &self.group(group)?.name;
+        let plugins = self.plugins(group);
+        let plugin = plugins
+            .get(plugin)
+            .ok_or_else(|| SelectionError::UnknownPlugin {
+                group: name.clone(),
+                plugin: format!("#{plugin}"),
+            })?;
+        Ok(plugin.plugin_type(self.env, &self.step_flags()))
+    }
+
+    /// Checks the selections of the current step against the group and plugin types.
+    pub fn validate(&self) -> Result<(), SelectionError> {
+        let flags = self.step_flags();
+        for (idx, group) in self.groups().into_iter().enumerate() {
+            group.validate_selection(self.selected(idx), self.env, &flags)?;
+        }
+        Ok(())
+    }
+
+    /// The flags set by all visited steps up to and including the current one.
+    pub fn flags(&self) -> Flags {
+        self.flags_of(&self.history)
+    }
+
+    /// The flags the current step is presented with, as set by the visited
+    /// steps before it.
+    pub fn step_flags(&self) -> Flags {
+        self.flags_of(&self.history[..self.history.len().saturating_sub(1)])
+    }
+
+    /// Every selected plugin of the visited steps, in installation order.
+    pub fn selected_plugins(&self) -> Vec<(&'a InstallStep, &'a Group, &'a Plugin)> {
+        self.selected_plugins_of(&self.history)
+    }
+
+    /// Every plugin of the visited steps and whether it is selected.
+    pub fn choices(&self) -> Vec<PluginChoice<'a>> {
+        let mut list = Vec::new();
+        for (visited, &idx) in self.history.iter().enumerate() {
+            let step = &self.steps[idx];
+            let Some(selection) = self.selections[idx].as_ref() else {
+                continue;
+            };
+            let flags = self.flags_of(&self.history[..visited]);
+            for (group, selected) in step.groups.iter().zip(selection) {
+                for (pidx, plugin) in group.plugins.iter().enumerate() {
+                    list.push(PluginChoice {
+                        step: step.step,
+                        group: group.group,
+                        plugin,
+                        plugin_type: plugin.plugin_type(self.env, &flags),
+                        selected: selected.contains(&pidx),
+                    });
+                }
+            }
+        }
+        list
+    }
+
+    /// The files to install for the choices made so far.
+    pub fn install_plan(&self) -> InstallPlan {
+        self.config
+            .install_plan(self.choices(), self.env, &self.flags())
+    }
+
+    fn flags_of(&self, history: &[usize]) -> Flags {
+        let mut flags = Flags::new();
+        for (_, _, plugin) in self.selected_plugins_of(history) {
+            flags.extend(plugin.condition_flags.iter());
+        }
+        flags
+    }
+
+    fn selected_plugins_of(
+        &self,
+        history: &[usize],
+    ) -> Vec<(&'a InstallStep, &'a Group, &'a Plugin)> {
+        let mut list = Vec::new();
+        for &idx in history {
+            let step = &self.steps[idx];
+            let Some(selection) = self.selections[idx].as_ref() else {
+                continue;
+            };
+            for (group, selected) in step.groups.iter().zip(selection) {
+                for &plugin in selected {
+                    list.push((step.step, group.group, group.plugins[plugin]));
+                }
+            }
+        }
+        list
+    }
+
+    /// Moves to the next visible step, returns `None` when there is none and
+    /// the session is finished.
+    ///
+    /// The selections of the current step must be valid to move on.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> Result<Option<&'a InstallStep>, SelectionError> {
+        if self.finished {
+            return Ok(None);
+        }
+        self.validate()?;
+        self.advance();
+        Ok(self.current_step())
+    }
+
+    /// Moves back to the previously visited step. The selections made on the
+    /// step being left are kept, but no longer contribute to the flags.
+    pub fn previous(&mut self) -> Option<&'a InstallStep> {
+        if self.finished && !self.history.is_empty() {
+            self.finished = false;
+        } else if !self.finished && self.history.len() > 1 {
+            self.history.pop();
+        } else {
+            return None;
+        }
+        self.current_step()
+    }
+
+    fn current(&self) -> Option<usize> {
+        if self.finished {
+            None
+        } else {
+            self.history.last().copied()
+        }
+    }
+
+    fn advance(&mut self) {
+        let flags = self.flags();
+        let start = self.history.last().map(|idx| idx + 1).unwrap_or_default();
+
+        let next = (start..self.steps.len()).find(|&idx| {
+            self.steps[idx]
+                .step
+                .visible
+                .as_ref()
+                .map(|v| v.evaluate(self.env, &flags))
+                .unwrap_or(true)
+        });
+
+        match next {
+            Some(idx) => {
+                self.history.push(idx);
+                if self.selections[idx].is_none() {
+                    self.selections[idx] = Some(self.default_selection(idx));
+                }
+            }
+            None => self.finished = true,
+        }
+    }
+
+    fn default_selection(&self, idx: usize) -> Vec<Vec<usize>> {
+        let flags = self.step_flags();
+        self.steps[idx]
+            .groups
+            .iter()
+            .map(|g| {
+                let types = g
+                    .plugins
+                    .iter()
+                    .map(|p| p.plugin_type(self.env, &flags))
+                    .collect::<Vec<_>>();
+                let mut selection = types
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, t)| {
+                        matches!(t, PluginTypeEnum::Required | PluginTypeEnum::Recommended)
+                    })
+                    .map(|(i, _)| i)
+                    .collect::<Vec<_>>();
+
+                match g.group.plugins {
+                    GroupType::SelectAll(_) => {
+                        selection = (0..types.len())
+                            .filter(|&i| types[i] != PluginTypeEnum::NotUsable)
+                            .collect()
+                    }
+                    GroupType::SelectExactlyOne(_) | GroupType::SelectAtMostOne(_) => {
+                        selection.truncate(1);
+                    }
+                    _ => {}
+                }
+                if selection.is_empty()
+                    && matches!(
+                        g.group.plugins,
+                        GroupType::SelectExactlyOne(_) | GroupType::SelectAtLeastOne(_)
+                    )
+                {
+                    selection.extend(types.iter().position(|t| *t != PluginTypeEnum::NotUsable));
+                }
+                selection
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InstallerSession;
+    use crate::{Config, DependencyState, MemoryEnvironment, PluginTypeEnum, SelectionError};
+
+    const XML: &str = r#"
+        <config xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance"
+            xsi:noNamespaceSchemaLocation="http://qconsulting.ca/fo3/ModConfig5.0.xsd">
+
+            <moduleName>Example Mod</moduleName>
+
+            <moduleDependencies operator="And">
+                <fileDependency file="depend1.plugin" state="Active"/>
+            </moduleDependencies>
+
+            <installSteps order="Explicit">
+                <installStep name="Choose Option">
+                    <optionalFileGroups order="Explicit">
+                        <group name="Select an option:" type="SelectExactlyOne">
+                            <plugins order="Explicit">
+                                <plugin name="Option A">
+                                    <description>Select this to install Option A!</description>
+                                    <conditionFlags>
+                                        <flag name="option">a</flag>
+                                    </conditionFlags>
+                                    <typeDescriptor>
+                                        <type name="Optional"/>
+                                    </typeDescriptor>
+                                </plugin>
+                                <plugin name="Option B">
+                                    <description>Select this to install Option B!</description>
+                                    <conditionFlags>
+                                        <flag name="option">b</flag>
+                                    </conditionFlags>
+                                    <typeDescriptor>
+                                        <type name="Recommended"/>
+                                    </typeDescriptor>
+                                </plugin>
+                            </plugins>
+                        </group>
+                    </optionalFileGroups>
+                </installStep>
+                <installStep name="Option A Extras">
+                    <visible>
+                        <flagDependency flag="option" value="a"/>
+                    </visible>
+                    <optionalFileGroups order="Explicit">
+                        <group name="Extras" type="SelectAny">
+                            <plugins order="Explicit">
+                                <plugin name="Extra A">
+                                    <description>Extra A</description>
+                                    <conditionFlags>
+                                        <flag name="extra">a</flag>
+                                    </conditionFlags>
+                                    <typeDescriptor>
+                                        <type name="Optional"/>
+                                    </typeDescriptor>
+                                </plugin>
+                            </plugins>
+                        </group>
+                    </optionalFileGroups>
+                </installStep>
+                <installStep name="Patches">
+                    <visible>
+                        <dependencies operator="Or">
+                            <fileDependency file="other.esp" state="Active"/>
+                            <flagDependency flag="extra" value="a"/>
+                        </dependencies>
+                    </visible>
+                    <optionalFileGroups order="Explicit">
+                        <group name="Patches" type="SelectAll">
+                            <plugins order="Explicit">
+                                <plugin name="Patch">
+                                    <description>Patch</description>
+                                    <typeDescriptor>
+                                        <type name="Required"/>
+                                    </typeDescriptor>
+                                </plugin>
+                            </plugins>
+                        </group>
+                    </optionalFileGroups>
+                </installStep>
+            </installSteps>
+        </config>
+        "#;
+
+    #[test]
+    pub fn skips_invisible_steps() {
+        let config = Config::try_from(XML).unwrap();
+        let env = MemoryEnvironment::new();
+        let mut session = InstallerSession::new(&config, &env);
+
+        assert!(!session.module_dependencies_met());
+        assert_eq!(session.current_step().unwrap().name, "Choose Option");
+        assert_eq!(session.selected(0), &[1]);
+        assert_eq!(session.flags().get("option"), "b");
+
+        assert!(session.next().unwrap().is_none());
+        assert!(session.is_finished());
+        assert_eq!(session.selected_plugins().len(), 1);
+    }
+
+    #[test]
+    pub fn next_and_previous() {
+        let config = Config::try_from(XML).unwrap();
+        let env = MemoryEnvironment::new();
+        let mut session = InstallerSession::new(&config, &env);
+
+        session.select(0, 0).unwrap();
+        assert_eq!(session.selected(0), &[0]);
+        assert_eq!(session.next().unwrap().unwrap().name, "Option A Extras");
+        assert!(session.selected(0).is_empty());
+
+        session.select(0, 0).unwrap();
+        assert_eq!(session.next().unwrap().unwrap().name, "Patches");
+        assert_eq!(session.selected(0), &[0]);
+        assert_eq!(session.flags().get("extra"), "a");
+
+        assert_eq!(session.previous().unwrap().name, "Option A Extras");
+        assert_eq!(session.previous().unwrap().name, "Choose Option");
+        assert_eq!(session.flags().get("extra"), "");
+        assert!(session.previous().is_none());
+
+        session.select(0, 1).unwrap();
+        assert_eq!(session.flags().get("option"), "b");
+        assert!(session.next().unwrap().is_none());
+
+        let names = session
+            .selected_plugins()
+            .iter()
+            .map(|(_, _, p)| p.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["Option B"]);
+
+        assert_eq!(session.previous().unwrap().name, "Choose Option");
+        session.select(0, 0).unwrap();
+        assert_eq!(session.next().unwrap().unwrap().name, "Option A Extras");
+        assert_eq!(session.selected(0), &[0]);
+    }
+
+    #[test]
+    pub fn environment_visibility() {
+        let config = Config::try_from(XML).unwrap();
+        let env = MemoryEnvironment::new()
+            .with_file("depend1.plugin", DependencyState::Active)
+            .with_file("other.esp", DependencyState::Active);
+        let mut session = InstallerSession::new(&config, &env);
+
+        assert!(session.module_dependencies_met());
+        assert_eq!(session.next().unwrap().unwrap().name, "Patches");
+        assert_eq!(session.selected(0), &[0]);
+        assert!(session.next().unwrap().is_none());
+        assert!(session.next().unwrap().is_none());
+    }
+
+    #[test]
+    pub fn invalid_selection() {
+        let config = Config::try_from(XML).unwrap();
+        let env = MemoryEnvironment::new().with_file("other.esp", DependencyState::Active);
+        let mut session = InstallerSession::new(&config, &env);
+
+        session.deselect(0, 1).unwrap();
+        assert_eq!(
+            session.next(),
+            Err(SelectionError::ExactlyOne {
+                group: "Select an option:".to_string(),
+                selected: 0
+            })
+        );
+        assert_eq!(session.current_step().unwrap().name, "Choose Option");
+
+        session.select(0, 0).unwrap();
+        assert_eq!(session.next().unwrap().unwrap().name, "Option A Extras");
+        assert_eq!(session.next().unwrap().unwrap().name, "Patches");
+
+        assert_eq!(session.plugin_type(0, 0), Ok(PluginTypeEnum::Required));
+        session.deselect(0, 0).unwrap();
+        assert!(matches!(
+            session.validate(),
+            Err(SelectionError::RequiredNotSelected { .. })
+        ));
+
+        assert!(matches!(
+            session.select(9, 0),
+            Err(SelectionError::UnknownGroup { .. })
+        ));
+        assert!(matches!(
+            session.set_selected(0, &[0, 9]),
+            Err(SelectionError::UnknownPlugin { .. })
+        ));
+        assert!(session.deselect(0, 9).is_err());
+        assert!(matches!(
+            session.plugin_type(0, 9),
+            Err(SelectionError::UnknownPlugin { .. })
+        ));
+        assert!(session.selected(9).is_empty());
+        assert!(session.plugins(9).is_empty());
+
+        // A finished session has no groups to select in.
+        session.select(0, 0).unwrap();
+        assert_eq!(session.next(), Ok(None));
+        assert!(matches!(
+            session.select(0, 0),
+            Err(SelectionError::UnknownGroup { .. })
+        ));
+        assert!(matches!(
+            session.plugin_type(0, 0),
+            Err(SelectionError::UnknownGroup { .. })
+        ));
+        assert!(session.set_selected(0, &[]).is_err());
+        assert!(!session.is_selected(0, 0));
+    }
+}
@@ -0,0 +1,310 @@
+use crate::{
+    enumerate::{walk, Budget},
+    Choices, Config, FileOrigin, FileTypeEnum, FlagFlow, FomodPackage, GameEnvironment,
+    InstallPlan, InstallerSession, PackageSource, PathNormalizer, PlannedFile,
+};
+
+/// What a combination of choices should achieve.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Target {
+    /// Something is installed at this destination, compared case-insensitively.
+    File(String),
+    /// A flag has this value after the last step; `""` for an unset flag.
+    Flag { flag: String, value: String },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Solution {
+    /// Choices which reach the target.
+    Found(Choices),
+    /// No valid combination of choices reaches the target.
+    Impossible,
+    /// The limit was reached before a combination was found, or the target
+    /// may only be reached through folders whose contents are not known.
+    Unknown,
+}
+
+/// Whether a plan installs the target file.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Installs {
+    Yes,
+    No,
+    /// Through a folder whose contents are not known.
+    Maybe,
+}
+
+/// Searches the valid combinations of selections for one reaching a target.
+///
+/// Combinations are tried as `Enumerator` walks them. Targets which the
+/// flag flow or the files of the config rule out are impossible without
+/// trying any.
+pub struct Solver<'a, E: GameEnvironment + ?Sized> {
+    config: &'a Config,
+    env: &'a E,
+    limit: Option<usize>,
+}
+impl<'a, E: GameEnvironment + ?Sized> Solver<'a, E> {
+    pub fn new(config: &'a Config, env: &'a E) -> Self {
+        Self {
+            config,
+            env,
+            limit: None,
+        }
+    }
+    /// Gives up after trying `limit` selections of groups.
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Finds choices reaching `target`.
+    ///
+    /// Without the package the contents of folders are not known, so a file
+    /// target is only found by a `<file>` with that exact destination. When
+    /// it could be inside a `<folder>` instead, the solution is `Unknown`;
+    /// see `solve_in` for files inside folders.
+    pub fn solve(&self, target: &Target) -> Solution {
+        let paths = PathNormalizer::default();
+        self.search(target, &paths, |plan, path| {
+            let mut installs = Installs::No;
+            for pf in &plan.files {
+                let Ok(destination) = pf.destination().map(|d| d.to_lowercase()) else {
+                    continue;
+                };
+                if !pf.is_folder() && destination == path {
+                    return Installs::Yes;
+                }
+                if pf.is_folder()
+                    && (destination.is_empty()
+                        || path
+                            .strip_prefix(destination.as_str())
+                            .is_some_and(|rest| rest.starts_with('/')))
+                {
+                    installs = Installs::Maybe;
+                }
+            }
+            installs
+        })
+    }
+
+    /// Like `solve`, with the files of folders looked up in `package`, whose
+    /// config should be the one searched.
+    pub fn solve_in<S: PackageSource>(
+        &self,
+        package: &FomodPackage<S>,
+        target: &Target,
+    ) -> Solution {
+        self.search(target, package.paths(), |plan, path| {
+            let found = package
+                .operations(plan)
+                .is_ok_and(|ops| ops.iter().any(|op| op.destination.to_lowercase() == path));
+            if found {
+                Installs::Yes
+            } else {
+                Installs::No
+            }
+        })
+    }
+
+    /// `installs` tells whether a plan installs the normalized, lowercase path.
+    fn search(
+        &self,
+        target: &Target,
+        paths: &PathNormalizer,
+        installs: impl Fn(&InstallPlan, &str) -> Installs,
+    ) -> Solution {
+        let path = match target {
+            Target::File(path) => match paths.destination(path) {
+                Ok(path) => path.to_lowercase(),
+                Err(_) => return Solution::Impossible,
+            },
+            Target::Flag { .. } => String::new(),
+        };
+        let possible = match target {
+            // Each file on its own, as a source missing from the package
+            // only fails the plans it is part of.
+            Target::File(_) => every_file(self.config)
+                .into_iter()
+                .any(|pf| installs(&InstallPlan { files: vec![pf] }, &path) != Installs::No),
+            Target::Flag { flag, value } => FlagFlow::new(self.config).after().can_be(flag, value),
+        };
+        if !possible {
+            return Solution::Impossible;
+        }
+        let reached = |session: &InstallerSession<'a, E>| match target {
+            Target::File(_) => installs(&session.install_plan(), &path),
+            Target::Flag { flag, value } if session.flags().get(flag) == value => Installs::Yes,
+            Target::Flag { .. } => Installs::No,
+        };
+
+        let mut found = None;
+        let mut maybe = false;
+        let mut budget = Budget::new(self.limit);
+        let mut session = InstallerSession::new(self.config, self.env);
+        walk(&mut session, self.env, &mut budget, &mut |session| {
+            match reached(session) {
+                Installs::Yes => {
+                    found = Some(Choices::from_session(session));
+                    return false;
+                }
+                Installs::Maybe => maybe = true,
+                Installs::No => {}
+            }
+            true
+        });
+        match found {
+            Some(choices) => Solution::Found(choices),
+            None if budget.exceeded || maybe => Solution::Unknown,
+            None => Solution::Impossible,
+        }
+    }
+}
+
+/// Every file of the config, whatever the choices.
+fn every_file(config: &Config) -> Vec<PlannedFile> {
+    let mut files = Vec::new();
+    let mut add = |list: &[FileTypeEnum], origin: FileOrigin| {
+        files.extend(list.iter().map(|file| PlannedFile {
+            file: file.clone(),
+            origin: origin.clone(),
+        }));
+    };
+
+    add(&config.required_install_files, FileOrigin::Required);
+    for step in config.install_steps.list() {
+        for group in step.optional_file_groups.list() {
+            for plugin in group.plugins.inner().list() {
+                let origin = FileOrigin::Plugin {
+                    step: step.name.clone(),
+                    group: group.name.clone(),
+                    plugin: plugin.name.clone(),
+                };
+                add(&plugin.files, origin);
+            }
+        }
+    }
+    for (idx, cip) in config.conditional_file_installs.iter().enumerate() {
+        add(&cip.files, FileOrigin::Conditional(idx));
+    }
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Solution, Solver, Target};
+    use crate::{Config, FomodPackage, MemoryEnvironment, MemorySource};
+
+    const XML: &str = r#"
+        <config>
+            <moduleName>Example Mod</moduleName>
+            <installSteps order="Explicit">
+                <installStep name="Options">
+                    <optionalFileGroups order="Explicit">
+                        <group name="Textures" type="SelectExactlyOne">
+                            <plugins order="Explicit">
+                                <plugin name="SD">
+                                    <description/>
+                                    <files><folder source="sd" destination="textures"/></files>
+                                </plugin>
+                                <plugin name="HD">
+                                    <description/>
+                                    <files><folder source="hd" destination="textures"/></files>
+                                    <conditionFlags><flag name="hd">On</flag></conditionFlags>
+                                </plugin>
+                            </plugins>
+                        </group>
+                        <group name="Patches" type="SelectAny">
+                            <plugins order="Explicit">
+                                <plugin name="Patch">
+                                    <description/>
+                                    <files><file source="patch.esp"/></files>
+                                    <typeDescriptor><type name="NotUsable"/></typeDescriptor>
+                                </plugin>
+                            </plugins>
+                        </group>
+                    </optionalFileGroups>
+                </installStep>
+                <installStep name="HD Options">
+                    <visible><flagDependency flag="hd" value="On"/></visible>
+                    <optionalFileGroups order="Explicit">
+                        <group name="Size" type="SelectExactlyOne">
+                            <plugins order="Explicit">
+                                <plugin name="2K">
+                                    <description/>
+                                    <files><file source="2k.ini" destination="hd.ini"/></files>
+                                </plugin>
+                                <plugin name="4K">
+                                    <description/>
+                                    <conditionFlags><flag name="size">4K</flag></conditionFlags>
+                                </plugin>
+                            </plugins>
+                        </group>
+                    </optionalFileGroups>
+                </installStep>
+            </installSteps>
+        </config>
+        "#;
+
+    #[test]
+    pub fn solve() {
+        let config = Config::try_from(XML).unwrap();
+        let env = MemoryEnvironment::new();
+        let solver = Solver::new(&config, &env);
+        let flag = |flag: &str, value: &str| Target::Flag {
+            flag: flag.to_string(),
+            value: value.to_string(),
+        };
+
+        let Solution::Found(choices) = solver.solve(&flag("size", "4K")) else {
+            panic!("size can be 4K");
+        };
+        let session = choices.replay(&config, &env).unwrap();
+        assert_eq!(session.flags().get("size"), "4K");
+        assert!(matches!(
+            solver.solve(&Target::File("HD.ini".to_string())),
+            Solution::Found(c) if c.steps[1].groups[0].plugins[0].name == "2K"
+        ));
+        assert!(matches!(
+            solver.solve(&flag("size", "")),
+            Solution::Found(c) if c.steps.len() == 1
+        ));
+
+        // Nothing sets size to 8K, and patch.esp is never usable.
+        assert_eq!(solver.solve(&flag("size", "8K")), Solution::Impossible);
+        assert_eq!(
+            solver.solve(&Target::File("patch.esp".to_string())),
+            Solution::Impossible
+        );
+        assert_eq!(
+            solver.solve(&Target::File("../hd.ini".to_string())),
+            Solution::Impossible
+        );
+        assert_eq!(
+            Solver::new(&config, &env)
+                .with_limit(1)
+                .solve(&flag("size", "4K")),
+            Solution::Unknown
+        );
+
+        // The contents of folders are only known with the package.
+        let target = Target::File("Textures/a.dds".to_string());
+        assert_eq!(solver.solve(&target), Solution::Unknown);
+        assert_eq!(
+            solver.solve(&Target::File("texturesa.dds".to_string())),
+            Solution::Impossible
+        );
+        assert_eq!(
+            solver.solve(&Target::File("textures".to_string())),
+            Solution::Impossible
+        );
+        let source = MemorySource::new()
+            .with_file("fomod/ModuleConfig.xml", XML)
+            .with_file("sd/b.dds", "sd b")
+            .with_file("hd/A.dds", "hd a");
+        let package = FomodPackage::new(source).unwrap();
+        assert!(matches!(
+            solver.solve_in(&package, &target),
+            Solution::Found(c) if c.steps[0].groups[0].plugins[0].name == "HD"
+        ));
+    }
+}
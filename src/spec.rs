@@ -1,82 +1,236 @@
+mod encoding;
 pub mod types;
 
-use std::io::BufReader;
+pub use encoding::decode;
+
+use std::io::{BufReader, Read};
 
 use quick_xml::{
-    de::{from_reader, from_str},
-    DeError,
+    de::Deserializer,
+    events::{BytesDecl, Event},
+    se::Serializer,
+    Reader, Writer,
 };
-use serde::Deserialize;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
-use types::{ConditionalFileInstallList, FileList, HeaderImage, ModuleDependency, StepList};
+use crate::{error::Step, Error};
+use types::{
+    ConditionalFileInstallList, FileList, GroupType, HeaderImage, ModuleDependency,
+    PluginTypeDescriptorEnum, PluginTypeEnum, StepList,
+};
 
-#[derive(Debug, Deserialize, PartialEq)]
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
 pub struct Info {
-    #[serde(rename = "Name")]
+    #[serde(rename = "Name", skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
-    #[serde(rename = "Description")]
+    #[serde(rename = "Description", skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
-    #[serde(rename = "Version")]
+    #[serde(rename = "Version", skip_serializing_if = "Option::is_none")]
     pub version: Option<String>,
-    #[serde(rename = "Author")]
+    #[serde(rename = "Author", skip_serializing_if = "Option::is_none")]
     pub author: Option<String>,
-    #[serde(rename = "Website")]
+    #[serde(rename = "Website", skip_serializing_if = "Option::is_none")]
     pub website: Option<String>,
-    #[serde(rename = "CategoryId")]
+    #[serde(rename = "CategoryId", skip_serializing_if = "Option::is_none")]
     pub category_id: Option<usize>,
 }
+impl Info {
+    pub fn to_xml_string(&self) -> Result<String, Error> {
+        to_xml_string(self, "fomod", &[])
+    }
+    pub fn write_to<W: std::io::Write>(&self, writer: W) -> Result<(), Error> {
+        write_to(writer, &self.to_xml_string()?)
+    }
+}
 impl TryFrom<&str> for Info {
-    type Error = DeError;
+    type Error = Error;
 
     fn try_from(string: &str) -> Result<Self, Self::Error> {
         from_str(string)
     }
 }
+impl TryFrom<&[u8]> for Info {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        from_str(&decode(bytes)?)
+    }
+}
 impl<T> TryFrom<BufReader<T>> for Info
 where
     T: std::io::Read,
 {
-    type Error = DeError;
+    type Error = Error;
 
     fn try_from(reader: BufReader<T>) -> Result<Self, Self::Error> {
-        from_reader(reader)
+        Self::try_from(read_all(reader)?.as_slice())
     }
 }
 
-#[derive(Debug, Deserialize, PartialEq)]
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
 pub struct Config {
     #[serde(rename = "moduleName")]
     pub module_name: String,
 
-    #[serde(rename = "moduleImage")]
+    #[serde(rename = "moduleImage", skip_serializing_if = "Option::is_none")]
     pub module_image: Option<HeaderImage>,
 
-    #[serde(rename = "moduleDependencies")]
+    #[serde(rename = "moduleDependencies", skip_serializing_if = "Option::is_none")]
     pub module_dependencies: Option<ModuleDependency>,
 
-    #[serde(rename = "requiredInstallFiles")]
+    #[serde(
+        rename = "requiredInstallFiles",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub required_install_files: Option<FileList>,
 
-    #[serde(rename = "installSteps")]
+    #[serde(rename = "installSteps", skip_serializing_if = "Option::is_none")]
     pub install_steps: Option<StepList>,
 
-    #[serde(rename = "conditionalFileInstalls")]
+    #[serde(
+        rename = "conditionalFileInstalls",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub conditional_file_installs: Option<ConditionalFileInstallList>,
 }
+impl Config {
+    /// Writes this config as a ModuleConfig 5.0 document.
+    pub fn to_xml_string(&self) -> Result<String, Error> {
+        to_xml_string(self, "config", MODULE_CONFIG_SCHEMA)
+    }
+    pub fn write_to<W: std::io::Write>(&self, writer: W) -> Result<(), Error> {
+        write_to(writer, &self.to_xml_string()?)
+    }
+}
 impl TryFrom<&str> for Config {
-    type Error = DeError;
+    type Error = Error;
 
     fn try_from(string: &str) -> Result<Self, Self::Error> {
-        from_str(string)
+        let config: Self = from_str(string)?;
+        config.check(string)?;
+        Ok(config)
+    }
+}
+impl TryFrom<&[u8]> for Config {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        Self::try_from(decode(bytes)?.as_str())
     }
 }
 impl<T> TryFrom<BufReader<T>> for Config
 where
     T: std::io::Read,
 {
-    type Error = DeError;
+    type Error = Error;
 
     fn try_from(reader: BufReader<T>) -> Result<Self, Self::Error> {
-        from_reader(reader)
+        Self::try_from(read_all(reader)?.as_slice())
+    }
+}
+
+impl Config {
+    /// Checks what the schema cannot: no group needs more plugins selected
+    /// than its type allows.
+    fn check(&self, xml: &str) -> Result<(), Error> {
+        let child = |name: &str, nth: Option<usize>| Step::Child(name.to_string(), nth);
+        let steps = self.install_steps.iter().flat_map(|s| &s.install_step);
+
+        for (i, step) in steps.enumerate() {
+            for (j, group) in step.optional_file_groups.group.iter().enumerate() {
+                if !matches!(
+                    group.typ,
+                    GroupType::SelectExactlyOne | GroupType::SelectAtMostOne
+                ) {
+                    continue;
+                }
+                let mut required = group.plugins.plugin.iter().enumerate().filter(|(_, p)| {
+                    matches!(
+                        p.type_descriptor.as_ref().map(|td| &td.value),
+                        Some(PluginTypeDescriptorEnum::PluginType(t))
+                            if t.name == PluginTypeEnum::Required
+                    )
+                });
+                if let (Some((_, first)), Some((k, second))) = (required.next(), required.next()) {
+                    let steps = [
+                        child("installSteps", None),
+                        child("installStep", Some(i)),
+                        child("optionalFileGroups", None),
+                        child("group", Some(j)),
+                        child("plugins", None),
+                        child("plugin", Some(k)),
+                    ];
+                    let message = format!(
+                        "group '{}' is {:?}, but plugins '{}' and '{}' are both required",
+                        group.name, group.typ, first.name, second.name
+                    );
+                    return Err(Error::semantic(xml, &steps, message));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+const MODULE_CONFIG_SCHEMA: &[(&str, &str)] = &[
+    ("xmlns:xsi", "http://www.w3.org/2001/XMLSchema-instance"),
+    (
+        "xsi:noNamespaceSchemaLocation",
+        "http://qconsulting.ca/fo3/ModConfig5.0.xsd",
+    ),
+];
+
+pub(crate) fn to_xml_string<T: Serialize>(
+    value: &T,
+    root: &str,
+    attributes: &[(&str, &str)],
+) -> Result<String, Error> {
+    let mut body = String::new();
+    value.serialize(Serializer::with_root(&mut body, Some(root))?)?;
+    // A raw carriage return would be normalized away when reading the document back.
+    let body = body.replace('\r', "&#xD;");
+
+    // The serializer would indent text content of elements with attributes, so the
+    // compact output is indented afterwards instead.
+    let mut reader = Reader::from_str(&body);
+    let mut writer = Writer::new_with_indent(Vec::new(), b' ', 4);
+    writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("utf-8"), None)))?;
+
+    let mut is_root = true;
+    loop {
+        let event = match reader.read_event()? {
+            Event::Eof => break,
+            Event::Start(mut e) if is_root => {
+                e.extend_attributes(attributes.iter().copied());
+                Event::Start(e)
+            }
+            Event::Empty(mut e) if is_root => {
+                e.extend_attributes(attributes.iter().copied());
+                Event::Empty(e)
+            }
+            e => e,
+        };
+        is_root = false;
+        writer.write_event(event)?;
     }
+
+    let mut xml = String::from_utf8(writer.into_inner())
+        .map_err(|e| Error::Encoding(format!("document is not valid UTF-8: {e}")))?;
+    xml.push('\n');
+    Ok(xml)
+}
+
+pub(crate) fn read_all<R: Read>(mut reader: R) -> Result<Vec<u8>, Error> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+pub(crate) fn write_to<W: std::io::Write>(mut writer: W, xml: &str) -> Result<(), Error> {
+    Ok(writer.write_all(xml.as_bytes())?)
+}
+
+pub(crate) fn from_str<T: DeserializeOwned>(xml: &str) -> Result<T, Error> {
+    serde_path_to_error::deserialize(&mut Deserializer::from_str(xml))
+        .map_err(|e| Error::deserialize(xml, e))
 }
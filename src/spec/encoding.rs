@@ -0,0 +1,150 @@
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252};
+
+use crate::Error;
+
+/// Decodes an xml document into a string.
+///
+/// The encoding is taken from the byte order mark, or else from the xml
+/// declaration. UTF-8 is assumed when neither is present; a document which
+/// claims to be UTF-8 but is not, is read as Windows-1252 instead, as written
+/// by many older tools.
+pub fn decode(bytes: &[u8]) -> Result<String, Error> {
+    if let Some((encoding, bom_length)) = Encoding::for_bom(bytes) {
+        return decode_with(encoding, &bytes[bom_length..]);
+    }
+
+    match bytes {
+        [0x3C, 0x00, 0x3F, 0x00, ..] => return decode_with(UTF_16LE, bytes),
+        [0x00, 0x3C, 0x00, 0x3F, ..] => return decode_with(UTF_16BE, bytes),
+        _ => {}
+    }
+
+    match declared_encoding(bytes) {
+        Some(encoding) if encoding != UTF_8 => decode_with(encoding, bytes),
+        _ => decode_with(UTF_8, bytes).or_else(|_| decode_with(WINDOWS_1252, bytes)),
+    }
+}
+
+fn decode_with(encoding: &'static Encoding, bytes: &[u8]) -> Result<String, Error> {
+    encoding
+        .decode_without_bom_handling_and_without_replacement(bytes)
+        .map(|s| strip_declared_encoding(s.into_owned()))
+        .ok_or_else(|| Error::Encoding(format!("document is not valid {}", encoding.name())))
+}
+
+/// The encoding named in the xml declaration, if it is a single byte encoding.
+///
+/// A document which reaches this point is ASCII compatible, so a declaration of
+/// UTF-16 is ignored.
+fn declared_encoding(bytes: &[u8]) -> Option<&'static Encoding> {
+    let declaration = declaration(bytes)?;
+    let label = attribute(declaration, "encoding")?;
+
+    Encoding::for_label(label.as_bytes())
+        .filter(|e| *e != UTF_16LE && *e != UTF_16BE)
+        .map(Encoding::output_encoding)
+}
+
+fn declaration(bytes: &[u8]) -> Option<&str> {
+    let start = bytes.windows(5).position(|w| w == b"<?xml")?;
+    let end = start + bytes[start..].windows(2).position(|w| w == b"?>")?;
+    std::str::from_utf8(&bytes[start..end]).ok()
+}
+
+fn attribute<'a>(declaration: &'a str, name: &str) -> Option<&'a str> {
+    let rest = &declaration[declaration.find(name)? + name.len()..];
+    let rest = rest.trim_start().strip_prefix('=')?.trim_start();
+    let quote = rest.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    let rest = &rest[1..];
+    Some(&rest[..rest.find(quote)?])
+}
+
+/// The decoded string is UTF-8, whatever the declaration claims.
+fn strip_declared_encoding(mut xml: String) -> String {
+    let label = declaration(xml.as_bytes())
+        .and_then(|d| attribute(d, "encoding"))
+        .map(|label| {
+            let start = label.as_ptr() as usize - xml.as_ptr() as usize;
+            start..start + label.len()
+        });
+    if let Some(label) = label {
+        xml.replace_range(label, "utf-8");
+    }
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::decode;
+    use crate::{spec::Config as SpecConfig, Config, Info};
+
+    const XML: &str = r#"<?xml version="1.0" encoding="utf-16"?>
+<config xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:noNamespaceSchemaLocation="http://qconsulting.ca/fo3/ModConfig5.0.xsd">
+    <moduleName>Café Mod</moduleName>
+</config>
+"#;
+
+    fn utf16(xml: &str, bom: bool, big_endian: bool) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let units = bom
+            .then_some('\u{FEFF}' as u16)
+            .into_iter()
+            .chain(xml.encode_utf16());
+        for unit in units {
+            if big_endian {
+                bytes.extend(unit.to_be_bytes());
+            } else {
+                bytes.extend(unit.to_le_bytes());
+            }
+        }
+        bytes
+    }
+
+    #[test]
+    pub fn utf16_documents() {
+        for (bom, big_endian) in [(true, false), (true, true), (false, false), (false, true)] {
+            let bytes = utf16(XML, bom, big_endian);
+            let config = Config::try_from(bytes.as_slice()).unwrap();
+            assert_eq!(config.module_name, "Café Mod");
+        }
+    }
+
+    #[test]
+    pub fn utf8_documents() {
+        let mut bytes = vec![0xEF, 0xBB, 0xBF];
+        bytes.extend(XML.replace("utf-16", "UTF-8").as_bytes());
+        let config = SpecConfig::try_from(bytes.as_slice()).unwrap();
+        assert_eq!(config.module_name, "Café Mod");
+
+        let reader = std::io::BufReader::new(&bytes[3..]);
+        assert_eq!(Config::try_from(reader).unwrap().module_name, "Café Mod");
+    }
+
+    #[test]
+    pub fn windows1252_documents() {
+        let xml = XML.replace("utf-16", "windows-1252").replace('é', "\u{0}");
+        let mut bytes = xml.into_bytes();
+        let idx = bytes.iter().position(|b| *b == 0).unwrap();
+        bytes[idx] = 0xE9;
+
+        assert_eq!(
+            decode(&bytes).unwrap().lines().next().unwrap(),
+            r#"<?xml version="1.0" encoding="utf-8"?>"#
+        );
+        assert_eq!(
+            Config::try_from(bytes.as_slice()).unwrap().module_name,
+            "Café Mod"
+        );
+
+        // Undeclared and not UTF-8.
+        let bytes = b"<fomod><Name>Caf\xE9</Name></fomod>";
+        assert_eq!(Info::try_from(&bytes[..]).unwrap().name.unwrap(), "Café");
+    }
+
+    #[test]
+    pub fn invalid_documents() {
+        let mut bytes = utf16(XML, true, false);
+        bytes.push(0x3C);
+        assert!(Config::try_from(bytes.as_slice()).is_err());
+    }
+}
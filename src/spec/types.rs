@@ -41,6 +41,7 @@ pub enum PluginTypeDescriptorEnum {
 
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct DependencyPluginType {
+    #[serde(rename = "defaultType")]
     pub default_type: PluginType,
     pub patterns: DependencyPatternList,
 }
@@ -52,7 +53,7 @@ pub struct DependencyPatternList {
 
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct DependencyPattern {
-    pub dependencies: CompositeDependency,
+    pub dependencies: ModuleDependency,
     #[serde(rename = "type")]
     pub typ: PluginType,
 }
@@ -71,7 +72,8 @@ pub struct InstallStep {
     #[serde(rename = "@name")]
     pub name: String,
 
-    pub visible: Option<CompositeDependency>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub visible: Option<ModuleDependency>,
 
     #[serde(rename = "optionalFileGroups")]
     pub optional_file_groups: GroupList,
@@ -79,9 +81,9 @@ pub struct InstallStep {
 
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct ModuleDependency {
-    #[serde(rename = "@operator")]
+    #[serde(rename = "@operator", default)]
     pub operator: DependencyOperator,
-    #[serde(rename = "$value")]
+    #[serde(rename = "$value", default)]
     pub list: Vec<CompositeDependency>,
 }
 
@@ -114,6 +116,14 @@ impl From<SetConditionFlag> for FlagDependency {
         }
     }
 }
+impl From<FlagDependency> for SetConditionFlag {
+    fn from(fd: FlagDependency) -> Self {
+        Self {
+            name: fd.flag,
+            flag_value: fd.value,
+        }
+    }
+}
 
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct VersionDependency {
@@ -136,8 +146,11 @@ pub enum DependencyState {
     Missing,
 }
 
-#[derive(Copy, Clone, Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(
+    Copy, Clone, Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord, Hash, Default,
+)]
 pub enum DependencyOperator {
+    #[default]
     And,
     Or,
 }
@@ -161,12 +174,22 @@ pub enum FileTypeEnum {
 pub struct FileType {
     #[serde(rename = "@source")]
     pub source: String,
-    #[serde(rename = "@destination")]
+    #[serde(
+        rename = "@destination",
+        default,
+        deserialize_with = "present_string",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub destination: Option<String>,
-    #[serde(rename = "@alwaysInstall")]
+    #[serde(rename = "@alwaysInstall", skip_serializing_if = "Option::is_none")]
     pub always_install: Option<String>,
-    #[serde(rename = "@installIfUsable", default = "false_bool")]
+    #[serde(
+        rename = "@installIfUsable",
+        default = "false_bool",
+        skip_serializing_if = "is_false"
+    )]
     pub install_if_usable: bool,
+    #[serde(rename = "@priority", skip_serializing_if = "Option::is_none")]
     pub priority: Option<isize>,
 }
 
@@ -212,13 +235,15 @@ pub struct Plugin {
 
     pub description: String,
 
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub image: Option<Image>,
 
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub files: Option<FileList>,
-    #[serde(rename = "conditionFlags")]
+    #[serde(rename = "conditionFlags", skip_serializing_if = "Option::is_none")]
     pub condition_flags: Option<ConditionFlagList>,
 
-    #[serde(rename = "typeDescriptor")]
+    #[serde(rename = "typeDescriptor", skip_serializing_if = "Option::is_none")]
     pub type_descriptor: Option<PluginTypeDescriptor>,
 }
 
@@ -230,12 +255,13 @@ pub struct Image {
 
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct HeaderImage {
-    #[serde(rename = "@path")]
+    #[serde(rename = "@path", skip_serializing_if = "Option::is_none")]
     pub path: Option<String>,
     #[serde(rename = "@showImage", default = "false_bool")]
     pub show_image: bool,
     #[serde(rename = "@showFade", default = "false_bool")]
     pub show_fade: bool,
+    #[serde(rename = "@height", skip_serializing_if = "Option::is_none")]
     pub height: Option<isize>,
 }
 
@@ -249,7 +275,7 @@ pub struct SetConditionFlag {
     #[serde(rename = "@name")]
     pub name: String,
 
-    #[serde(rename = "$value")]
+    #[serde(rename = "$text")]
     pub flag_value: String,
 }
 
@@ -265,10 +291,19 @@ pub struct ConditionalInstallPatternList {
 
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct ConditionalInstallPattern {
-    pub dependencies: CompositeDependency,
+    pub dependencies: ModuleDependency,
     pub files: FileList,
 }
 
 fn false_bool() -> bool {
     false
 }
+
+fn is_false(b: &bool) -> bool {
+    !b
+}
+
+/// Keeps an empty attribute as `Some("")`, which differs from a missing attribute.
+fn present_string<'de, D: serde::Deserializer<'de>>(d: D) -> Result<Option<String>, D::Error> {
+    String::deserialize(d).map(Some)
+}